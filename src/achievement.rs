@@ -0,0 +1,216 @@
+use crate::character::class::Category;
+use crate::character::Character;
+use crate::game::now;
+use crate::item::ring::Ring;
+use crate::log;
+use crate::quest::Event;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Long-term, cross-game goals. Unlike quests, achievements don't pay gold,
+/// are shared by every game played on this computer and survive hard resets.
+/// Hidden achievements are not described until they are unlocked.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, EnumIter)]
+pub enum Achievement {
+    FirstBlood,
+    Centurion,
+    Explorer,
+    Prestige,
+    Flawless,
+    GiantSlayer,
+    RichCorpse,
+    PowerCouple,
+}
+
+impl Achievement {
+    /// Return the (description, hidden, goal) of the achievement.
+    /// The goal is the amount of progress required to unlock it.
+    fn info(&self) -> (&'static str, bool, i32) {
+        match self {
+            Achievement::FirstBlood => ("win a battle", false, 1),
+            Achievement::Centurion => ("win 100 battles", false, 100),
+            Achievement::Explorer => ("reach 30 steps away from home", false, 30),
+            Achievement::Prestige => ("prestige your hero", false, 1),
+            Achievement::Flawless => ("win a battle without taking damage", true, 1),
+            Achievement::GiantSlayer => ("beat a legendary creature below level 20", true, 1),
+            Achievement::RichCorpse => ("die carrying 10000g or more", true, 1),
+            Achievement::PowerCouple => ("wear the ruling and revive rings together", true, 1),
+        }
+    }
+
+    /// Return the progress the given event represents for this achievement,
+    /// if any. Progress values are either increments or absolute, see `is_absolute`.
+    fn progress(&self, event: &Event, player: &Character, gold: i32) -> Option<i32> {
+        match (self, event) {
+            (Achievement::FirstBlood, Event::BattleWon { .. }) => Some(1),
+            (Achievement::Centurion, Event::BattleWon { .. }) => Some(1),
+            (Achievement::Explorer, Event::LocationVisited { location }) => {
+                Some(location.distance_from_home().len())
+            }
+            (Achievement::Prestige, Event::Prestige { .. }) => Some(1),
+            (Achievement::Flawless, Event::FlawlessVictory) => Some(1),
+            (Achievement::GiantSlayer, Event::BattleWon { enemy, .. })
+                if enemy.class.category == Category::Legendary && player.level < 20 =>
+            {
+                Some(1)
+            }
+            (Achievement::RichCorpse, Event::Died { .. }) if gold >= 10000 => Some(1),
+            (Achievement::PowerCouple, Event::ItemUsed { .. })
+                if player.wears(Ring::Ruling) && player.wears(Ring::Revive) =>
+            {
+                Some(1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the progress reported for this achievement is an absolute value
+    /// (e.g. a distance) instead of an increment.
+    fn is_absolute(&self) -> bool {
+        matches!(self, Achievement::Explorer)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Achievements {
+    /// Unix timestamp of each unlocked achievement.
+    unlocked: HashMap<Achievement, u64>,
+
+    /// Partial progress of the achievements that require multiple steps.
+    progress: HashMap<Achievement, i32>,
+}
+
+impl Achievements {
+    /// Update the progress of the pending achievements with the given event.
+    /// The hero and its gold are passed along to check for conditions not
+    /// included in the event.
+    pub fn handle(&mut self, event: &Event, player: &Character, gold: i32) {
+        for achievement in Achievement::iter() {
+            if self.unlocked.contains_key(&achievement) {
+                continue;
+            }
+
+            if let Some(progress) = achievement.progress(event, player, gold) {
+                let current = self.progress.entry(achievement).or_default();
+                if achievement.is_absolute() {
+                    *current = std::cmp::max(*current, progress);
+                } else {
+                    *current += progress;
+                }
+
+                let (description, _, goal) = achievement.info();
+                if *current >= goal {
+                    self.progress.remove(&achievement);
+                    self.unlocked.insert(achievement, now());
+                    log::achievement_unlocked(description);
+                }
+            }
+        }
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains_key(&achievement)
+    }
+
+    /// Return a (unlocked, description) list of achievements, showing the
+    /// unlock date or the current progress.
+    pub fn list(&self) -> Vec<(bool, String)> {
+        Achievement::iter()
+            .map(|achievement| {
+                let (description, hidden, goal) = achievement.info();
+                if let Some(timestamp) = self.unlocked.get(&achievement) {
+                    (true, format!("{} {}", description, format_date(*timestamp)))
+                } else if hidden {
+                    (false, String::from("???"))
+                } else if goal > 1 {
+                    let progress = self.progress.get(&achievement).unwrap_or(&0);
+                    (false, format!("{} {}/{}", description, progress, goal))
+                } else {
+                    (false, description.to_string())
+                }
+            })
+            .collect()
+    }
+}
+
+/// Format a unix timestamp as a yyyy-mm-dd date.
+fn format_date(timestamp: u64) -> String {
+    // days to civil date conversion, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::tests::location_from;
+
+    #[test]
+    fn progress() {
+        let mut achievements = Achievements::default();
+        let player = Character::player();
+        let enemy = Character::player();
+        let location = location_from("~/1");
+
+        for _ in 0..99 {
+            achievements.handle(
+                &Event::BattleWon {
+                    enemy: &enemy,
+                    location: location.clone(),
+                },
+                &player,
+                0,
+            );
+        }
+        assert!(achievements.is_unlocked(Achievement::FirstBlood));
+        assert!(!achievements.is_unlocked(Achievement::Centurion));
+        assert!(achievements
+            .list()
+            .contains(&(false, String::from("win 100 battles 99/100"))));
+
+        achievements.handle(
+            &Event::BattleWon {
+                enemy: &enemy,
+                location,
+            },
+            &player,
+            0,
+        );
+        assert!(achievements.is_unlocked(Achievement::Centurion));
+    }
+
+    #[test]
+    fn hidden() {
+        let mut achievements = Achievements::default();
+        let player = Character::player();
+        assert!(achievements.list().contains(&(false, String::from("???"))));
+
+        let died = Event::Died {
+            cause: String::from("orc"),
+        };
+        achievements.handle(&died, &player, 9999);
+        assert!(!achievements.is_unlocked(Achievement::RichCorpse));
+        achievements.handle(&died, &player, 10000);
+        assert!(achievements.is_unlocked(Achievement::RichCorpse));
+    }
+
+    #[test]
+    fn date() {
+        assert_eq!("1970-01-01", format_date(0));
+        assert_eq!("2021-09-05", format_date(1630800000));
+        assert_eq!("2024-02-29", format_date(1709164800));
+    }
+}
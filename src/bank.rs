@@ -0,0 +1,224 @@
+use crate::game::Game;
+use crate::log;
+use crate::quest;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Percentage of each deposit charged by the bank.
+const DEPOSIT_FEE_PERCENT: i32 = 5;
+
+/// Percentage of the balance paid as interest.
+const INTEREST_PERCENT: i32 = 2;
+
+/// Amount of battles won between interest payments.
+const INTEREST_BATTLES: i32 = 10;
+
+/// Determines how much of the hero's gold is left in the tombstone on death.
+/// Gold that isn't dropped is lost.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathPenalty {
+    /// Drop the carried and the banked gold.
+    All,
+
+    /// Drop half of the carried and half of the banked gold.
+    Half,
+
+    /// Drop the carried gold, keeping the banked one.
+    #[default]
+    Unbanked,
+}
+
+/// Gold kept at home, outside of the hero, so it survives death.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Bank {
+    pub balance: i32,
+    pub penalty: DeathPenalty,
+
+    /// Battles won since the last interest payment.
+    battles: i32,
+}
+
+impl fmt::Display for DeathPenalty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DeathPenalty::All => "all",
+            DeathPenalty::Half => "half",
+            DeathPenalty::Unbanked => "unbanked",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Bank {
+    /// Store the given amount, minus the deposit fee.
+    /// Return the fee charged.
+    pub fn deposit(&mut self, amount: i32) -> i32 {
+        let fee = amount * DEPOSIT_FEE_PERCENT / 100;
+        self.balance += amount - fee;
+        fee
+    }
+
+    /// Take up to the given amount from the balance.
+    /// Return the amount withdrawn.
+    pub fn withdraw(&mut self, amount: i32) -> i32 {
+        let amount = std::cmp::min(amount, self.balance);
+        self.balance -= amount;
+        amount
+    }
+
+    /// Count a won battle, paying interest on the balance every few ones.
+    /// Return the interest paid, if any.
+    pub fn battle_won(&mut self) -> i32 {
+        self.battles += 1;
+        if self.battles < INTEREST_BATTLES {
+            return 0;
+        }
+
+        self.battles = 0;
+        let interest = self.balance * INTEREST_PERCENT / 100;
+        self.balance += interest;
+        interest
+    }
+
+    /// Apply the death penalty to the carried gold and the bank balance.
+    /// Return the amount of gold to drop in the hero's tombstone.
+    pub fn death_penalty(&mut self, carried: i32) -> i32 {
+        match self.penalty {
+            DeathPenalty::All => carried + self.withdraw(self.balance),
+            DeathPenalty::Half => carried / 2 + self.withdraw(self.balance / 2),
+            DeathPenalty::Unbanked => carried,
+        }
+    }
+}
+
+/// Move gold from the hero to the bank. Deposit all if no amount is given.
+pub fn deposit(game: &mut Game, amount: Option<i32>) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Bank is only available at home.");
+    }
+
+    let amount = amount.unwrap_or(game.gold);
+    if amount <= 0 {
+        bail!("Nothing to deposit.");
+    }
+    if amount > game.gold {
+        bail!("Not enough gold.");
+    }
+
+    game.gold -= amount;
+    let fee = game.bank.deposit(amount);
+    log::bank_deposit(amount, fee);
+    quest::gold_spent(game, fee);
+    Ok(())
+}
+
+/// Move gold from the bank to the hero. Withdraw all if no amount is given.
+pub fn withdraw(game: &mut Game, amount: Option<i32>) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Bank is only available at home.");
+    }
+
+    let amount = amount.unwrap_or(game.bank.balance);
+    if amount <= 0 {
+        bail!("Nothing to withdraw.");
+    }
+    if amount > game.bank.balance {
+        bail!("Not enough gold in the bank.");
+    }
+
+    game.gold += game.bank.withdraw(amount);
+    log::bank_withdraw(amount);
+    Ok(())
+}
+
+/// Print the bank balance and death penalty.
+pub fn status(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Bank is only available at home.");
+    }
+
+    log::bank_status(&game.bank);
+    Ok(())
+}
+
+/// Change how much gold is dropped when the hero dies.
+pub fn set_penalty(game: &mut Game, penalty: DeathPenalty) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Bank is only available at home.");
+    }
+
+    game.bank.penalty = penalty;
+    log::bank_status(&game.bank);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bank_at_home() {
+        let mut game = Game::new();
+        game.gold = 200;
+        assert!(deposit(&mut game, Some(300)).is_err());
+        assert!(deposit(&mut game, Some(100)).is_ok());
+        assert_eq!(100, game.gold);
+        assert_eq!(95, game.bank.balance);
+        assert!(deposit(&mut game, None).is_ok());
+        assert_eq!(0, game.gold);
+        assert_eq!(190, game.bank.balance);
+
+        // the balance survives death
+        game.reset();
+        assert!(withdraw(&mut game, Some(200)).is_err());
+        assert!(withdraw(&mut game, None).is_ok());
+        assert_eq!(190, game.gold);
+        assert_eq!(0, game.bank.balance);
+    }
+
+    #[test]
+    fn deposit_withdraw() {
+        let mut bank = Bank::default();
+        assert_eq!(50, bank.deposit(1000));
+        assert_eq!(950, bank.balance);
+
+        assert_eq!(500, bank.withdraw(500));
+        assert_eq!(450, bank.withdraw(1000));
+        assert_eq!(0, bank.balance);
+    }
+
+    #[test]
+    fn interest() {
+        let mut bank = Bank {
+            balance: 1000,
+            ..Bank::default()
+        };
+        for _ in 0..INTEREST_BATTLES - 1 {
+            assert_eq!(0, bank.battle_won());
+        }
+        assert_eq!(20, bank.battle_won());
+        assert_eq!(1020, bank.balance);
+        assert_eq!(0, bank.battle_won());
+    }
+
+    #[test]
+    fn death_penalty() {
+        let mut bank = Bank {
+            balance: 1000,
+            ..Bank::default()
+        };
+        assert_eq!(100, bank.death_penalty(100));
+        assert_eq!(1000, bank.balance);
+
+        bank.penalty = DeathPenalty::Half;
+        assert_eq!(550, bank.death_penalty(100));
+        assert_eq!(500, bank.balance);
+
+        bank.penalty = DeathPenalty::All;
+        assert_eq!(600, bank.death_penalty(100));
+        assert_eq!(0, bank.balance);
+    }
+}
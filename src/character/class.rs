@@ -1,3 +1,4 @@
+use crate::item::key::Key;
 use crate::randomizer::{random, Randomizer};
 use once_cell::sync::OnceCell;
 use rand::prelude::SliceRandom;
@@ -42,12 +43,20 @@ pub struct Class {
     pub category: Category,
 
     pub inflicts: Option<(super::StatusEffect, u32)>,
+
+    /// Whether the class can try to open locked chests without a key.
+    #[serde(default)]
+    pub lockpick: bool,
+
+    /// The kind of weapon that gets a bonus when wielded by the class.
+    #[serde(default)]
+    pub affinity: Option<Key>,
 }
 
 /// Determines whether the class is intended for a Player or, if it's for an enemy,
 /// How rare it is (how frequently it should appear).
 /// Enables easier customization of the classes via an external file.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, std::hash::Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, std::hash::Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum Category {
     Player,
@@ -85,11 +94,28 @@ impl Class {
             .copied()
     }
 
+    /// Find an enemy class of any category by its name.
+    pub fn enemy_by_name(name: &str) -> Option<&'static Self> {
+        [Category::Common, Category::Rare, Category::Legendary]
+            .into_iter()
+            .flat_map(Self::of)
+            .find(|class| class.name == name)
+    }
+
     pub fn random(category: Category) -> &'static Self {
         let mut rng = rand::thread_rng();
         Self::of(category).choose(&mut rng).unwrap()
     }
 
+    /// The names of the player classes with affinity for the given weapon kind.
+    pub fn with_affinity(key: &Key) -> Vec<String> {
+        Self::of(Category::Player)
+            .iter()
+            .filter(|class| class.affinity.as_ref() == Some(key))
+            .map(|class| class.name.clone())
+            .collect()
+    }
+
     pub fn names(category: Category) -> HashSet<String> {
         Self::of(category)
             .iter()
@@ -1,44 +1,167 @@
 use super::{class::Category, class::Class, Character};
+use crate::item::curse::Curse;
 use crate::item::ring::Ring;
 use crate::location;
 use crate::log;
 use crate::randomizer::{random, Randomizer};
+use crate::scan::Scan;
+use once_cell::sync::OnceCell;
 use rand::prelude::SliceRandom;
 use rand::Rng;
+use serde::Deserialize;
+
+/// A kind of directory where specific enemies dwell, e.g. `node_modules`.
+/// All the conditions that are set must match the location.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Lair {
+    /// A directory name in the path, or an absolute path that contains it.
+    dir: Option<String>,
+
+    /// Extension of the files that must be in the directory.
+    files: Option<String>,
+
+    #[serde(default)]
+    min_files: usize,
+
+    #[serde(default)]
+    min_entries: usize,
+
+    /// Classes spawned at the lair. If empty, random ones are used.
+    #[serde(default)]
+    enemies: Vec<String>,
+
+    /// Lowest category of the random classes spawned at the lair.
+    category: Option<Category>,
+
+    /// Added to the enemy level.
+    #[serde(default)]
+    level: i32,
+
+    /// Enemies appear twice as often.
+    #[serde(default)]
+    swarm: bool,
+}
+
+static LAIRS: OnceCell<Vec<Lair>> = OnceCell::new();
+
+/// Customize the lair definitions based on an input yaml byte array.
+pub fn load(bytes: &[u8]) {
+    LAIRS.set(serde_yaml::from_slice(bytes).unwrap()).unwrap();
+}
+
+fn lairs() -> &'static Vec<Lair> {
+    LAIRS.get_or_init(|| serde_yaml::from_slice(include_bytes!("lairs.yaml")).unwrap())
+}
+
+impl Lair {
+    /// Return the first lair that matches the given location and its contents.
+    fn find(location: &location::Location, scan: &Scan) -> Option<&'static Self> {
+        lairs().iter().find(|lair| lair.matches(location, scan))
+    }
+
+    fn matches(&self, location: &location::Location, scan: &Scan) -> bool {
+        let path = location.path();
+        let dir_matches = self.dir.as_ref().is_none_or(|dir| {
+            if dir.starts_with('/') {
+                path.starts_with(dir)
+            } else {
+                path.components()
+                    .any(|component| component.as_os_str() == dir.as_str())
+            }
+        });
+        let files_match = self
+            .files
+            .as_ref()
+            .is_none_or(|extension| scan.files(extension) >= self.min_files.max(1));
+        dir_matches && files_match && scan.entries >= self.min_entries
+    }
+
+    /// Pick one of the lair enemies, if it defines any that exist.
+    fn random_class(&self) -> Option<&'static Class> {
+        let mut rng = rand::thread_rng();
+        let classes: Vec<_> = self
+            .enemies
+            .iter()
+            .filter_map(|name| Class::enemy_by_name(name))
+            .collect();
+        classes.choose(&mut rng).copied()
+    }
+}
 
 /// Randomly spawn an enemy character at the given location, based on the
 /// current character stats.
 /// The distance from home will influence the enemy frequency and level.
 /// Under certain conditions, special (quest-related) enemies may be spawned.
-pub fn spawn(location: &location::Location, player: &Character) -> Option<Character> {
-    if player.enemies_evaded() {
+/// The directory contents matter too: empty directories are safe rooms,
+/// recently modified ones have more encounters and lairs spawn their own enemies.
+pub fn spawn(location: &location::Location, player: &Character, scan: &Scan) -> Option<Character> {
+    if player.enemies_evaded() || scan.is_safe() {
         return None;
     }
 
     let distance = location.distance_from_home();
-    if random().should_enemy_appear(&distance) {
-        // try spawning "special" enemies if conditions are met, otherwise
-        // a random one for the current location
-        let (class, level) = spawn_gorthaur(player, location)
-            .or_else(|| spawn_shadow(player, location))
-            .or_else(|| spawn_dev(player, location))
-            .unwrap_or_else(|| spawn_random(player, &distance));
-
-        let level = random().enemy_level(level);
-        let enemy = Character::new(class, level);
-        log::enemy_appears(&enemy, location);
-        Some(enemy)
+    let lair = Lair::find(location, scan);
+    // the lure curse, swarming lairs and active directories double
+    // the chance of an enemy appearing
+    let lured = player.has_curse(Curse::Lure) && random().should_enemy_appear(&distance);
+    let swarmed = lair.is_some_and(|lair| lair.swarm) && random().should_enemy_appear(&distance);
+    let active = scan.is_active() && random().should_enemy_appear(&distance);
+    if random().should_enemy_appear(&distance) || lured || swarmed || active {
+        Some(generate(location, player, scan))
+    } else {
+        None
+    }
+}
+
+/// Randomly spawn an enemy that catches the hero while camping at the
+/// given location. The further from home, the more likely the ambush.
+pub fn ambush(location: &location::Location, player: &Character, scan: &Scan) -> Option<Character> {
+    if player.enemies_evaded() || scan.is_safe() {
+        return None;
+    }
+
+    if random().camp_ambush(&location.distance_from_home()) {
+        Some(generate(location, player, scan))
     } else {
         None
     }
 }
 
+/// Spawn an enemy for the hero to attack, e.g. by throwing a bomb.
+/// There are no enemies to find at home or in safe rooms.
+pub fn provoke(
+    location: &location::Location,
+    player: &Character,
+    scan: &Scan,
+) -> Option<Character> {
+    if location.is_home() || scan.is_safe() {
+        return None;
+    }
+    Some(generate(location, player, scan))
+}
+
+fn generate(location: &location::Location, player: &Character, scan: &Scan) -> Character {
+    // try spawning "special" enemies if conditions are met, otherwise
+    // a random one for the current location, tougher inside dungeons
+    let (class, level) = spawn_gorthaur(player, location)
+        .or_else(|| spawn_shadow(player, location))
+        .or_else(|| spawn_dev(player, location))
+        .unwrap_or_else(|| {
+            let lair = Lair::find(location, scan);
+            let (class, level) = spawn_random(player, &location.distance_from_home(), lair);
+            let bonus = scan.repo.as_ref().map_or(0, |repo| repo.level_bonus());
+            (class, level + bonus)
+        });
+
+    let level = random().enemy_level(level);
+    let enemy = Character::new(class, level);
+    log::enemy_appears(&enemy, location);
+    enemy
+}
+
 /// Final boss, only appears at level +100 when wearing the ruling ring
 fn spawn_gorthaur(player: &Character, location: &location::Location) -> Option<(Class, i32)> {
-    let wearing_ring =
-        player.left_ring == Some(Ring::Ruling) || player.right_ring == Some(Ring::Ruling);
-
-    if wearing_ring && location.distance_from_home().len() >= 100 {
+    if player.wears(Ring::Ruling) && location.distance_from_home().len() >= 100 {
         let mut class = Class::player_first().clone();
         class.name = String::from("gorthaur");
         class.hp.0 *= 2;
@@ -81,7 +204,12 @@ fn spawn_dev(player: &Character, location: &location::Location) -> Option<(Class
 }
 
 /// Choose an enemy randomly, with higher chance to difficult enemies the further from home.
-fn spawn_random(player: &Character, distance: &location::Distance) -> (Class, i32) {
+/// Lairs can restrict the choice to their own enemies or to the harder categories.
+fn spawn_random(
+    player: &Character,
+    distance: &location::Distance,
+    lair: Option<&Lair>,
+) -> (Class, i32) {
     // the weights for each group of enemies are different depending on the distance
     // the further from home, the bigger the chance to find difficult enemies
     let (w_common, w_rare, w_legendary) = match distance {
@@ -99,15 +227,30 @@ fn spawn_random(player: &Character, distance: &location::Distance) -> (Class, i3
         (Category::Legendary, w_legendary),
     ];
 
+    let min_category = lair
+        .and_then(|lair| lair.category.clone())
+        .unwrap_or(Category::Common);
+    let weights: Vec<_> = weights
+        .into_iter()
+        .filter(|(category, _)| *category >= min_category)
+        .collect();
+
+    // if no category has weight at this distance, use the lair minimum one
     let category = weights
         .as_slice()
         .choose_weighted(&mut rng, |(_c, weight)| *weight)
-        .unwrap()
-        .0
-        .clone();
+        .map_or(min_category.clone(), |(category, _)| category.clone());
 
-    let level = std::cmp::max(player.level / 10 + distance.len() - 1, 1);
-    (Class::random(category).clone(), level)
+    let lair_level = lair.map_or(0, |lair| lair.level);
+    let level = std::cmp::max(player.level / 10 + distance.len() - 1 + lair_level, 1);
+
+    // after each prestige enemies get tougher to make up for the hero bonus,
+    // rounding up so the ones near home are too
+    let level = level + (level * player.prestige + 1) / 2;
+    let class = lair
+        .and_then(Lair::random_class)
+        .unwrap_or_else(|| Class::random(category));
+    (class.clone(), level)
 }
 
 #[cfg(test)]
@@ -122,37 +265,94 @@ mod tests {
         let d3 = location::Distance::from(3);
         let d10 = location::Distance::from(10);
 
-        assert_eq!(1, spawn_random(&player, &d1).1);
-        assert_eq!(1, spawn_random(&player, &d2).1);
-        assert_eq!(2, spawn_random(&player, &d3).1);
-        assert_eq!(9, spawn_random(&player, &d10).1);
+        assert_eq!(1, spawn_random(&player, &d1, None).1);
+        assert_eq!(1, spawn_random(&player, &d2, None).1);
+        assert_eq!(2, spawn_random(&player, &d3, None).1);
+        assert_eq!(9, spawn_random(&player, &d10, None).1);
 
         player.level = 5;
-        assert_eq!(1, spawn_random(&player, &d1).1);
-        assert_eq!(1, spawn_random(&player, &d2).1);
-        assert_eq!(2, spawn_random(&player, &d3).1);
-        assert_eq!(9, spawn_random(&player, &d10).1);
+        assert_eq!(1, spawn_random(&player, &d1, None).1);
+        assert_eq!(1, spawn_random(&player, &d2, None).1);
+        assert_eq!(2, spawn_random(&player, &d3, None).1);
+        assert_eq!(9, spawn_random(&player, &d10, None).1);
 
         player.level = 10;
-        assert_eq!(1, spawn_random(&player, &d1).1);
-        assert_eq!(2, spawn_random(&player, &d2).1);
-        assert_eq!(3, spawn_random(&player, &d3).1);
-        assert_eq!(10, spawn_random(&player, &d10).1);
+        assert_eq!(1, spawn_random(&player, &d1, None).1);
+        assert_eq!(2, spawn_random(&player, &d2, None).1);
+        assert_eq!(3, spawn_random(&player, &d3, None).1);
+        assert_eq!(10, spawn_random(&player, &d10, None).1);
+
+        player.prestige = 1;
+        assert_eq!(2, spawn_random(&player, &d1, None).1);
+        assert_eq!(3, spawn_random(&player, &d2, None).1);
+        assert_eq!(5, spawn_random(&player, &d3, None).1);
+        assert_eq!(15, spawn_random(&player, &d10, None).1);
+
+        player.prestige = 2;
+        assert_eq!(2, spawn_random(&player, &d1, None).1);
+        assert_eq!(20, spawn_random(&player, &d10, None).1);
+
+        // low level heroes meet tougher enemies near home too
+        player.level = 1;
+        player.prestige = 1;
+        assert_eq!(2, spawn_random(&player, &d1, None).1);
     }
 
     #[test]
     fn test_run_ring() {
         let mut player = Character::player();
         let location = location::tests::location_from("~/1/");
-        assert!(spawn(&location, &player).is_some());
+        assert!(spawn(&location, &player, &Scan::default()).is_some());
 
         player.equip_ring(Ring::Evade);
-        assert!(spawn(&location, &player).is_none());
+        assert!(spawn(&location, &player, &Scan::default()).is_none());
 
         player.equip_ring(Ring::Void);
-        assert!(spawn(&location, &player).is_none());
+        assert!(spawn(&location, &player, &Scan::default()).is_none());
 
         player.equip_ring(Ring::Void);
-        assert!(spawn(&location, &player).is_some());
+        assert!(spawn(&location, &player, &Scan::default()).is_some());
+    }
+
+    #[test]
+    fn safe_rooms() {
+        let player = Character::player();
+        let location = location::tests::location_from("~/1/");
+        let mut scan = Scan::default();
+        scan.readable = true;
+        assert!(spawn(&location, &player, &scan).is_none());
+
+        scan.entries = 1;
+        assert!(spawn(&location, &player, &scan).is_some());
+    }
+
+    #[test]
+    fn lairs() {
+        let player = Character::player();
+        let near = location::Distance::from(3);
+        let mut scan = Scan::default();
+
+        let location = location::tests::location_from("/home/dev/app/node_modules/lodash");
+        let lair = Lair::find(&location, &scan).unwrap();
+        let (class, level) = spawn_random(&player, &near, Some(lair));
+        assert!(["bug", "spider"].contains(&class.name.as_str()));
+        assert_eq!(1, level);
+
+        let location = location::tests::location_from("/tmp/build");
+        let lair = Lair::find(&location, &scan).unwrap();
+        assert_eq!("ghost", spawn_random(&player, &near, Some(lair)).0.name);
+
+        let location = location::tests::location_from("/home/dev/app/src");
+        assert!(Lair::find(&location, &scan).is_none());
+        scan.extensions.insert(String::from("rs"), 10);
+        let lair = Lair::find(&location, &scan).unwrap();
+        assert_eq!("crab", spawn_random(&player, &near, Some(lair)).0.name);
+
+        // huge directories only have rare enemies, even near home
+        scan.extensions.clear();
+        scan.entries = 1000;
+        let lair = Lair::find(&location, &scan).unwrap();
+        let class = spawn_random(&player, &near, Some(lair)).0;
+        assert_eq!(Category::Rare, class.category);
     }
 }
@@ -1,17 +1,22 @@
+use crate::item::curse::Curse;
 use crate::item::equipment;
+use crate::item::equipment::Affix;
 use crate::item::key::Key;
-use crate::item::ring::Ring;
+use crate::item::ring;
+use crate::item::ring::{Ring, RingSet};
 use crate::item::Item;
 use crate::log;
 use crate::randomizer::{random, Randomizer};
 use class::Class;
 use serde::{Deserialize, Serialize};
 use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet};
+use strum::IntoEnumIterator;
 
 pub mod class;
 pub mod enemy;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Character {
     pub class: Class,
@@ -29,10 +34,32 @@ pub struct Character {
 
     pub sword: Option<equipment::Equipment>,
     pub shield: Option<equipment::Equipment>,
+    pub helmet: Option<equipment::Equipment>,
+    pub armor: Option<equipment::Equipment>,
+    pub boots: Option<equipment::Equipment>,
+    pub amulet: Option<equipment::Equipment>,
     pub left_ring: Option<Ring>,
     pub right_ring: Option<Ring>,
 
     pub status_effect: Option<StatusEffect>,
+
+    /// How many times the hero started over after beating the final boss.
+    /// Each tier grants a permanent stat bonus.
+    pub prestige: i32,
+
+    /// Accumulated tiredness from walking and fighting away from home.
+    /// High fatigue reduces speed and accuracy.
+    pub fatigue: i32,
+
+    /// Upgrade level of each kind of ring, kept across the hero's lifes.
+    /// Rings not included are at level 1.
+    pub ring_levels: HashMap<Ring, i32>,
+
+    /// Curses of the rings found cursed, kept until purified.
+    pub ring_curses: HashMap<Ring, Curse>,
+
+    /// Rings found but not identified yet.
+    pub unidentified_rings: HashSet<Ring>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
@@ -42,6 +69,16 @@ pub enum StatusEffect {
     Poison,
 }
 
+impl std::fmt::Display for StatusEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            StatusEffect::Burn => "burn",
+            StatusEffect::Poison => "poison",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Outcome of an attack attempt.
 /// This affects primarily how the attack is displayed.
 pub enum AttackType {
@@ -55,6 +92,11 @@ pub enum AttackType {
 pub struct Dead;
 pub struct ClassNotFound;
 
+pub const MAX_FATIGUE: i32 = 100;
+
+/// Fatigue level above which the hero stats start to suffer.
+const FATIGUE_THRESHOLD: i32 = 50;
+
 impl Default for Character {
     fn default() -> Self {
         Character::player()
@@ -84,6 +126,10 @@ impl Character {
             class,
             sword: None,
             shield: None,
+            helmet: None,
+            armor: None,
+            boots: None,
+            amulet: None,
             left_ring: None,
             right_ring: None,
             level: 1,
@@ -95,6 +141,11 @@ impl Character {
             strength,
             speed,
             status_effect: None,
+            prestige: 0,
+            fatigue: 0,
+            ring_levels: HashMap::new(),
+            ring_curses: HashMap::new(),
+            unidentified_rings: HashSet::new(),
         };
 
         for _ in 1..level {
@@ -117,16 +168,25 @@ impl Character {
                 // if class change is done at level 1, it works as a game reset
                 // the player stats are regenerated with the new class
                 // if equipment was already set, it is preserved
-                let sword = self.sword.take();
-                let shield = self.shield.take();
+                let equipment = self.equipment_slots().map(|slot| slot.take());
                 let left_ring = self.left_ring.take();
                 let right_ring = self.right_ring.take();
+                let prestige = self.prestige;
+                let ring_levels = std::mem::take(&mut self.ring_levels);
+                let ring_curses = std::mem::take(&mut self.ring_curses);
+                let unidentified_rings = std::mem::take(&mut self.unidentified_rings);
 
                 *self = Self::new(class.clone(), 1);
-                self.sword = sword;
-                self.shield = shield;
+                for (slot, piece) in self.equipment_slots().into_iter().zip(equipment) {
+                    *slot = piece;
+                }
                 self.left_ring = left_ring;
                 self.right_ring = right_ring;
+                self.prestige = prestige;
+                self.ring_levels = ring_levels;
+                self.ring_curses = ring_curses;
+                self.unidentified_rings = unidentified_rings;
+                self.restore();
             } else {
                 self.class = class.clone();
 
@@ -230,6 +290,7 @@ impl Character {
     pub fn restore(&mut self) -> (i32, i32, bool) {
         let healed = self.status_effect.is_some();
         self.status_effect = None;
+        self.fatigue = 0;
         (
             self.update_hp(self.max_hp()).unwrap(),
             self.update_mp(self.max_mp()),
@@ -237,6 +298,33 @@ impl Character {
         )
     }
 
+    /// Recover the given fraction of hp, mp and fatigue, e.g. when camping
+    /// away from home. Returns the (recovered hp, recovered mp, recovered fatigue).
+    pub fn rest(&mut self, fraction: f64) -> (i32, i32, i32) {
+        let hp = (self.max_hp() as f64 * fraction).round() as i32;
+        let mp = (self.max_mp() as f64 * fraction).round() as i32;
+        let fatigue = (MAX_FATIGUE as f64 * fraction).round() as i32;
+        (
+            self.update_hp(hp).unwrap(),
+            self.update_mp(mp),
+            -self.add_fatigue(-fatigue),
+        )
+    }
+
+    /// Add or subtract fatigue, keeping it between 0 and MAX_FATIGUE.
+    /// Return the effectively changed amount.
+    pub fn add_fatigue(&mut self, amount: i32) -> i32 {
+        let previous = self.fatigue;
+        self.fatigue = (self.fatigue + amount).clamp(0, MAX_FATIGUE);
+        self.fatigue - previous
+    }
+
+    /// The total weight of items the character can carry, based on its
+    /// base strength.
+    pub fn carry_capacity(&self) -> i32 {
+        20 + self.strength / 2
+    }
+
     /// How many experience points are required to move to the next level.
     pub fn xp_for_next(&self) -> i32 {
         let exp = 1.5;
@@ -245,15 +333,47 @@ impl Character {
     }
 
     pub fn max_hp(&self) -> i32 {
-        self.modify_stat(self.max_hp, Ring::HP)
+        let armor = self.equipment_bonus(Key::Armor);
+        self.prestige_bonus(self.modify_stat(self.max_hp, Ring::HP)) + armor
+    }
+
+    /// Remove the given status effect, if the character suffers from it.
+    /// Return true if it was cured.
+    pub fn cure(&mut self, status: StatusEffect) -> bool {
+        if self.status_effect == Some(status) {
+            self.status_effect = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Return true if the character benefits from wearing equipment of the
+    /// given kind. Helmets only make sense for characters that use magic.
+    pub fn can_use(&self, key: &Key) -> bool {
+        *key != Key::Helmet || self.class.is_magic()
     }
 
     pub fn max_mp(&self) -> i32 {
-        self.modify_stat(self.max_mp, Ring::MP)
+        let helmet = if self.can_use(&Key::Helmet) {
+            self.equipment_bonus(Key::Helmet)
+        } else {
+            0
+        };
+        self.prestige_bonus(self.modify_stat(self.max_mp, Ring::MP)) + helmet
     }
 
     pub fn speed(&self) -> i32 {
-        self.modify_stat(self.speed, Ring::Speed)
+        let speed = self.prestige_bonus(self.modify_stat(self.speed, Ring::Speed))
+            + self.equipment_bonus(Key::Boots)
+            + self.weapon_bonus(|weapon| weapon.speed());
+
+        // each swift piece adds 10% speed
+        let speed = speed + speed * self.affix_count(Affix::Swift) / 10;
+
+        // above the threshold, each fatigue point takes 1% of the speed
+        let penalty = max(0, self.fatigue - FATIGUE_THRESHOLD);
+        max(1, speed - speed * penalty / 100)
     }
 
     /// Generate and log an attack of this character and apply its effects to
@@ -284,26 +404,62 @@ impl Character {
 
         log::attack(receiver, &attack_type, damage, mp_cost);
 
+        // each vampiric piece recovers 10% of the inflicted damage
+        let drained = damage * self.affix_count(Affix::Vampiric) / 10;
+        if drained > 0 && self.current_hp > 0 {
+            self.update_hp(drained).unwrap_or_default();
+        }
+
+        // equipment wears out with each landed hit
+        if damage > 0 {
+            self.wear_equipment(Key::Sword);
+            receiver.wear_equipment(Key::Shield);
+        }
+
         (xp, result)
     }
 
+    /// Inflict a fixed amount of damage to the receiver, e.g. with a thrown
+    /// item, ignoring its deffense. Return the xp gained.
+    pub fn throw(&self, receiver: &mut Self, damage: i32) -> i32 {
+        let xp = self.xp_gained(receiver, damage);
+        receiver.update_hp(-damage).unwrap_or_default();
+        log::attack(receiver, &AttackType::Regular, damage, 0);
+        xp
+    }
+
+    /// Reduce the durability of the sword or shield after a hit,
+    /// removing it if it breaks.
+    fn wear_equipment(&mut self, key: Key) {
+        let slot = match key {
+            Key::Sword => &mut self.sword,
+            Key::Shield => &mut self.shield,
+            _ => return,
+        };
+
+        if slot.as_mut().is_some_and(|equipment| equipment.wear()) {
+            let broken = slot.take().unwrap();
+            log::equipment_broke(self, &broken);
+        }
+    }
+
     /// If the double beat ring is equipped, attack the receiver.
     pub fn maybe_double_beat(&mut self, receiver: &mut Self) {
-        if receiver.current_hp > 0
-            && (self.left_ring == Some(Ring::Double) || self.right_ring == Some(Ring::Double))
-        {
+        if receiver.current_hp > 0 && self.wears(Ring::Double) {
             // assuming it's always the player and we don't need to handle death
             let _ = self.attack(receiver);
         }
     }
 
     /// If the counter attack ring is equipped randomly counter attack the receiver.
+    /// With the flurry set, counter-attacks strike twice.
     pub fn maybe_counter_attack(&mut self, receiver: &mut Self) {
-        let wearing_counter =
-            self.left_ring == Some(Ring::Counter) || self.right_ring == Some(Ring::Counter);
-        if wearing_counter && random().counter_attack() {
+        if self.wears(Ring::Counter) && random().counter_attack() {
             // assuming it's always the player and we don't need to handle death
             let _ = self.attack(receiver);
+            if self.has_ring_set(RingSet::Flurry) {
+                self.maybe_double_beat(receiver);
+            }
         }
     }
 
@@ -316,8 +472,7 @@ impl Character {
         died: Result<(), Dead>,
         already_revived: bool,
     ) -> Result<bool, Dead> {
-        let wearing_revive =
-            self.left_ring == Some(Ring::Revive) || self.right_ring == Some(Ring::Revive);
+        let wearing_revive = self.wears(Ring::Revive);
         match died {
             Ok(()) => Ok(already_revived),
             Err(Dead) if wearing_revive && !already_revived => {
@@ -335,9 +490,11 @@ impl Character {
     fn attack_type(&self, receiver: &Self) -> AttackType {
         let inflicted_status = random().inflicted(self.inflicted_status_effect(receiver));
 
-        if random().is_miss(self.speed(), receiver.speed()) {
+        if random().is_miss(self.speed(), receiver.speed())
+            || random().is_fatigue_miss(self.fatigue - FATIGUE_THRESHOLD)
+        {
             AttackType::Miss
-        } else if random().is_critical() {
+        } else if random().is_critical(self.critical_ratio()) {
             AttackType::Critical
         } else if let Some(status) = inflicted_status {
             AttackType::Effect(status)
@@ -372,8 +529,9 @@ impl Character {
     /// Amount of damage the character can inflict with physical atacks, given
     /// its strength and equipment. Magic using characters' strength is dimmed.
     pub fn physical_attack(&self) -> i32 {
-        let sword_str = self.sword.as_ref().map_or(0, |s| s.strength());
+        let sword_str = self.weapon_bonus(|weapon| weapon.strength());
         let attack = self.modify_stat(self.strength, Ring::Attack) + sword_str;
+        let attack = self.prestige_bonus(attack);
         if self.class.is_magic() {
             attack / 3
         } else {
@@ -386,14 +544,15 @@ impl Character {
     pub fn magic_attack(&self) -> i32 {
         if self.class.is_magic() {
             let base = self.strength * 3;
-            self.modify_stat(base, Ring::Magic)
+            self.prestige_bonus(self.modify_stat(base, Ring::Magic))
+                + self.weapon_bonus(|weapon| weapon.magic_strength())
         } else {
             0
         }
     }
 
     pub fn deffense(&self) -> i32 {
-        let shield_str = self.shield.as_ref().map_or(0, |s| s.strength());
+        let shield_str = self.equipment_bonus(Key::Shield);
         // base strength should be zero, subtract it from ring calculation
         shield_str + self.modify_stat(self.strength, Ring::Deffense) - self.strength
     }
@@ -422,24 +581,45 @@ impl Character {
 
     /// Return the status that this character's attack should inflict on the receiver.
     fn inflicted_status_effect(&self, receiver: &Self) -> Option<(StatusEffect, u32)> {
-        if receiver.left_ring == Some(Ring::Protect) || receiver.right_ring == Some(Ring::Protect) {
+        if receiver.wears(Ring::Protect) {
             return None;
         }
 
-        let ring_status = match (self.left_ring.as_ref(), self.right_ring.as_ref()) {
-            (Some(Ring::Poison), _) | (_, Some(Ring::Poison)) => Some((StatusEffect::Poison, 3)),
-            (Some(Ring::Fire), _) | (_, Some(Ring::Fire)) => Some((StatusEffect::Burn, 3)),
-            _ => None,
+        // with the fire spells set, every magic attack burns
+        let set_status = if self.has_ring_set(RingSet::FireSpells) && self.can_magic_attack() {
+            Some((StatusEffect::Burn, 1))
+        } else {
+            None
+        };
+
+        let ring_status = if self.wears(Ring::Poison) {
+            Some((StatusEffect::Poison, 3))
+        } else if self.wears(Ring::Fire) {
+            Some((StatusEffect::Burn, 3))
+        } else {
+            None
         };
 
-        let result = self.class.inflicts.or(ring_status);
+        let affix_status = if self.affix_count(Affix::Fire) > 0 {
+            Some((StatusEffect::Burn, 4))
+        } else {
+            None
+        };
+
+        let result = set_status
+            .or(self.class.inflicts)
+            .or(ring_status)
+            .or(affix_status);
         if let Some((status, _)) = result {
             // don't double-inflict if already has the same status
             if receiver.status_effect == Some(status) {
                 return None;
             }
         }
-        result
+
+        // an amulet makes the status less likely by its resistance percentage
+        let resistance = receiver.equipment_bonus(Key::Amulet);
+        result.map(|(status, ratio)| (status, ratio * 100 / (100 - resistance) as u32))
     }
 
     /// If the character has a status condition (e.g. poison) or an equipped
@@ -452,17 +632,15 @@ impl Character {
         let hp_unit = || random().damage(std::cmp::max(1, self.max_hp / 20));
         let mp_unit = || random().damage(std::cmp::max(1, self.max_mp / 20));
 
-        if self.left_ring == Some(Ring::RegenHP) || self.right_ring == Some(Ring::RegenHP) {
+        if self.wears(Ring::RegenHP) {
             hp_effect += hp_unit();
         }
 
-        if self.class.is_magic()
-            && (self.left_ring == Some(Ring::RegenMP) || self.right_ring == Some(Ring::RegenMP))
-        {
+        if self.class.is_magic() && self.wears(Ring::RegenMP) {
             mp_effect += mp_unit();
         }
 
-        if self.left_ring == Some(Ring::Ruling) || self.right_ring == Some(Ring::Ruling) {
+        if self.wears(Ring::Ruling) || self.has_curse(Curse::Drain) {
             hp_effect -= hp_unit();
         }
 
@@ -487,22 +665,140 @@ impl Character {
         std::cmp::max(1, (self.level / 5) * 5)
     }
 
+    /// All the equipment slots of the character, in a fixed order.
+    pub fn equipment_slots(&mut self) -> [&mut Option<equipment::Equipment>; 6] {
+        [
+            &mut self.sword,
+            &mut self.shield,
+            &mut self.helmet,
+            &mut self.armor,
+            &mut self.boots,
+            &mut self.amulet,
+        ]
+    }
+
+    /// Return the equipment slot that holds pieces of the given kind, if any.
+    pub fn slot(&mut self, key: &Key) -> Option<&mut Option<equipment::Equipment>> {
+        match key {
+            key if key.is_weapon() => Some(&mut self.sword),
+            Key::Shield => Some(&mut self.shield),
+            Key::Helmet => Some(&mut self.helmet),
+            Key::Armor => Some(&mut self.armor),
+            Key::Boots => Some(&mut self.boots),
+            Key::Amulet => Some(&mut self.amulet),
+            _ => None,
+        }
+    }
+
+    /// Return the currently equipped pieces.
+    pub fn equipment(&self) -> Vec<&equipment::Equipment> {
+        [
+            &self.sword,
+            &self.shield,
+            &self.helmet,
+            &self.armor,
+            &self.boots,
+            &self.amulet,
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Return the equipped piece of the given kind, if any.
+    pub fn equipped(&self, key: &Key) -> Option<&equipment::Equipment> {
+        self.equipment()
+            .into_iter()
+            .find(|piece| piece.key() == *key)
+    }
+
+    /// Put the given piece in its slot, returning the one it replaces.
+    /// Changes to max hp and mp are applied to the current values as well.
+    /// Wearing a piece identifies it.
+    pub fn equip(&mut self, mut piece: equipment::Equipment) -> Option<equipment::Equipment> {
+        piece.identify();
+        let (max_hp, max_mp) = (self.max_hp(), self.max_mp());
+        let previous = self.slot(&piece.key())?.replace(piece);
+        self.current_hp = max(1, self.current_hp + self.max_hp() - max_hp);
+        self.current_mp = max(0, self.current_mp + self.max_mp() - max_mp);
+        previous
+    }
+
+    /// The given stat of the equipped weapon, if any, boosted by 25% if the
+    /// weapon kind has affinity with the character class.
+    fn weapon_bonus(&self, stat: impl Fn(&equipment::Equipment) -> i32) -> i32 {
+        self.sword.as_ref().map_or(0, |weapon| {
+            let bonus = stat(weapon);
+            if self.class.affinity.as_ref() == Some(&weapon.key()) {
+                bonus + bonus / 4
+            } else {
+                bonus
+            }
+        })
+    }
+
+    /// How many of the equipped pieces have the given affix.
+    fn affix_count(&self, affix: Affix) -> i32 {
+        self.equipment()
+            .iter()
+            .filter(|piece| piece.has_affix(affix))
+            .count() as i32
+    }
+
+    /// The 1 in N chance of landing a critical hit. Axes double it.
+    fn critical_ratio(&self) -> u32 {
+        match self.sword.as_ref().map(|weapon| weapon.key()) {
+            Some(Key::Axe) => 10,
+            _ => 20,
+        }
+    }
+
+    /// Return true if the equipped weapon allows to attack first in battle.
+    pub fn strikes_first(&self) -> bool {
+        matches!(
+            self.sword.as_ref().map(|weapon| weapon.key()),
+            Some(Key::Bow)
+        )
+    }
+
+    /// The stat contribution of the equipped piece of the given kind, if any.
+    fn equipment_bonus(&self, key: Key) -> i32 {
+        self.equipped(&key).map_or(0, |piece| piece.strength())
+    }
+
     /// Equip the given ring and apply its side-effects.
     /// If already carrying two rings, the least recently equipped one is
-    /// removed, undoing its side-effects.
+    /// removed, undoing its side-effects. A cursed ring is never removed,
+    /// so the other one is replaced instead.
     pub fn equip_ring(&mut self, ring: Ring) -> Option<Ring> {
-        let removed = self.right_ring.take();
+        let removed = if self
+            .right_ring
+            .as_ref()
+            .is_some_and(|ring| self.ring_curses.contains_key(ring))
+        {
+            self.left_ring.take()
+        } else {
+            let removed = self.right_ring.take();
+            self.right_ring = self.left_ring.take();
+            removed
+        };
         self.unequip_ring_side_effect(&removed);
         self.equip_ring_side_effect(&ring);
-        self.right_ring = self.left_ring.replace(ring);
+        self.unidentified_rings.remove(&ring);
+        self.left_ring = Some(ring);
 
         removed
     }
 
     /// Remove the ring by the given name from the equipment (if any),
-    /// unapplying its side-effects.
+    /// unapplying its side-effects. Cursed rings can't be removed.
     pub fn unequip_ring(&mut self, name: &Key) -> Option<Ring> {
         match (self.left_ring.clone(), self.right_ring.clone()) {
+            (Some(ring), _) | (_, Some(ring))
+                if ring.key() == *name && self.ring_curses.contains_key(&ring) =>
+            {
+                None
+            }
             (Some(ring), _) if ring.key() == *name => {
                 let removed = self.left_ring.take();
                 self.unequip_ring_side_effect(&removed);
@@ -518,15 +814,128 @@ impl Character {
         }
     }
 
-    /// Return true if an evade ring is equipped, i.e. no enemies should appear.
+    /// Return true if the given ring is equipped in either hand.
+    /// Every ring effect should be checked through this function.
+    pub fn wears(&self, ring: Ring) -> bool {
+        self.left_ring.as_ref() == Some(&ring) || self.right_ring.as_ref() == Some(&ring)
+    }
+
+    /// Return true if the given ring is equipped and cursed.
+    pub fn wears_cursed(&self, ring: &Ring) -> bool {
+        self.wears(ring.clone()) && self.ring_curses.contains_key(ring)
+    }
+
+    /// Return true if the piece in the slot for the given kind of equipment
+    /// is cursed, so it can't be replaced.
+    pub fn is_slot_cursed(&self, key: &Key) -> bool {
+        self.equipment().into_iter().any(|piece| {
+            piece.curse().is_some()
+                && (piece.key() == *key || piece.key().is_weapon() && key.is_weapon())
+        })
+    }
+
+    /// Return the curses of the equipped rings and pieces.
+    pub fn curses(&self) -> Vec<Curse> {
+        let mut curses: Vec<Curse> = [&self.left_ring, &self.right_ring]
+            .into_iter()
+            .flatten()
+            .filter_map(|ring| self.ring_curses.get(ring).copied())
+            .collect();
+        curses.extend(self.equipment().iter().filter_map(|piece| piece.curse()));
+        curses
+    }
+
+    pub fn has_curse(&self, curse: Curse) -> bool {
+        self.curses().contains(&curse)
+    }
+
+    /// Remove the curses of the equipped rings and pieces.
+    /// Return true if there was any.
+    pub fn purify(&mut self) -> bool {
+        let mut purified = false;
+        for ring in [self.left_ring.clone(), self.right_ring.clone()]
+            .into_iter()
+            .flatten()
+        {
+            purified |= self.ring_curses.remove(&ring).is_some();
+        }
+        for piece in self.equipment_slots().into_iter().flatten() {
+            purified |= piece.purify();
+        }
+        purified
+    }
+
+    /// Return true if both rings of the given set are equipped.
+    pub fn has_ring_set(&self, set: RingSet) -> bool {
+        let (first, second) = set.rings();
+        self.wears(first) && self.wears(second)
+    }
+
+    /// Return the sets currently completed by the equipped rings.
+    pub fn ring_sets(&self) -> Vec<RingSet> {
+        RingSet::iter()
+            .filter(|set| self.has_ring_set(*set))
+            .collect()
+    }
+
+    /// Describe the ring effect, including its level, the sets it
+    /// completes with the other equipped ring and its curse.
+    pub fn describe_ring(&self, ring: &Ring) -> String {
+        let mut description = ring.describe();
+        if ring.is_upgradable() {
+            description.push_str(&format!(" (level {})", self.ring_level(ring)));
+        }
+        for set in self.ring_sets() {
+            let (first, second) = set.rings();
+            if *ring == first || *ring == second {
+                description.push_str(&format!("; {}: {}", set, set.describe()));
+            }
+        }
+        if let Some(curse) = self.ring_curses.get(ring) {
+            description.push_str(&format!("; cursed, {}", curse.describe()));
+        }
+        description
+    }
+
+    pub fn ring_level(&self, ring: &Ring) -> i32 {
+        self.ring_levels.get(ring).copied().unwrap_or(1)
+    }
+
+    /// Increase the level of the given kind of ring, making its effect
+    /// stronger. Return false if it can't be upgraded any further.
+    pub fn upgrade_ring(&mut self, ring: &Ring) -> bool {
+        let level = self.ring_level(ring);
+        if !ring.is_upgradable() || level >= ring::MAX_LEVEL {
+            return false;
+        }
+
+        // undo the side effects of the current level, if equipped
+        let equipped = self.wears(ring.clone());
+        if equipped {
+            self.unequip_ring_side_effect(&Some(ring.clone()));
+        }
+        self.ring_levels.insert(ring.clone(), level + 1);
+        if equipped {
+            self.equip_ring_side_effect(ring);
+        }
+        true
+    }
+
+    /// Return true if an evade ring is equipped, i.e. no enemies should appear,
+    /// unless a lure curse cancels it.
     pub fn enemies_evaded(&self) -> bool {
-        self.left_ring == Some(Ring::Evade) || self.right_ring == Some(Ring::Evade)
+        self.wears(Ring::Evade) && !self.has_curse(Curse::Lure)
+    }
+
+    /// Return true if the character can try to open locked chests without a key.
+    pub fn picks_locks(&self) -> bool {
+        self.is_player() && self.class.lockpick
     }
 
     /// Return true if a chest ring is equipped, i.e. the character should have double
     /// chance to find a chest.
     pub fn double_chests(&self) -> bool {
-        self.left_ring == Some(Ring::Chest) || self.right_ring == Some(Ring::Chest)
+        self.wears(Ring::Chest)
     }
 
     /// Return the gold that should be rewarded for beating an enemy of the given
@@ -535,10 +944,13 @@ impl Character {
         let level = max(1, enemy_level - self.level);
         let gold = random().gold_gained(level * 50);
 
+        // each lucky piece adds 25% gold
+        let gold = gold + gold * self.affix_count(Affix::Lucky) / 4;
+
         if self.level > enemy_level + 10 {
             // don't reward cheap victories
             0
-        } else if self.left_ring == Some(Ring::Gold) || self.right_ring == Some(Ring::Gold) {
+        } else if self.wears(Ring::Gold) {
             gold * 2
         } else {
             gold
@@ -550,10 +962,10 @@ impl Character {
     fn equip_ring_side_effect(&mut self, ring: &Ring) {
         match ring {
             Ring::HP => {
-                self.current_hp += (ring.factor() * self.max_hp as f64) as i32;
+                self.current_hp += (ring.factor(self.ring_level(ring)) * self.max_hp as f64) as i32;
             }
             Ring::MP => {
-                self.current_mp += (ring.factor() * self.max_mp as f64) as i32;
+                self.current_mp += (ring.factor(self.ring_level(ring)) * self.max_mp as f64) as i32;
             }
             _ => {}
         }
@@ -561,13 +973,16 @@ impl Character {
 
     /// Unapply the side-effects of the ring on the character.
     fn unequip_ring_side_effect(&mut self, ring: &Option<Ring>) {
+        let factor = ring
+            .as_ref()
+            .map_or(0.0, |ring| ring.factor(self.ring_level(ring)));
         match ring {
             Some(Ring::HP) => {
-                let to_remove = (ring.as_ref().unwrap().factor() * self.max_hp as f64) as i32;
+                let to_remove = (factor * self.max_hp as f64) as i32;
                 self.current_hp = max(1, self.current_hp - to_remove);
             }
             Some(Ring::MP) => {
-                let to_remove = (ring.as_ref().unwrap().factor() * self.max_mp as f64) as i32;
+                let to_remove = (factor * self.max_mp as f64) as i32;
                 self.current_mp = max(1, self.current_mp - to_remove);
             }
             _ => {}
@@ -577,15 +992,21 @@ impl Character {
     /// If either ring matches the given one, apply the ring effect
     /// to the given base stat, e.g. for an HP ring increase the base HP.
     fn modify_stat(&self, base: i32, ring: Ring) -> i32 {
+        let ring_factor = ring.factor(self.ring_level(&ring));
         let mut factor = 1.0;
         if self.left_ring.as_ref() == Some(&ring) {
-            factor += ring.factor();
+            factor += ring_factor;
         }
         if self.right_ring.as_ref() == Some(&ring) {
-            factor += ring.factor();
+            factor += ring_factor;
         }
         (base as f64 * factor).round() as i32
     }
+
+    /// Apply the permanent bonus earned by prestiging: +10% per tier.
+    fn prestige_bonus(&self, base: i32) -> i32 {
+        base + base * self.prestige / 10
+    }
 }
 
 #[cfg(test)]
@@ -1154,6 +1575,69 @@ mod tests {
         assert_eq!(20, char.modify_stat(10, Ring::HP));
     }
 
+    #[test]
+    fn test_ring_upgrade() {
+        let mut char = new_plain_stats_char();
+        assert_eq!(1, char.ring_level(&Ring::HP));
+        assert!(!char.upgrade_ring(&Ring::Void));
+
+        char.equip_ring(Ring::HP);
+        assert_eq!(15, char.max_hp());
+        assert_eq!(15, char.current_hp);
+
+        assert!(char.upgrade_ring(&Ring::HP));
+        assert_eq!(2, char.ring_level(&Ring::HP));
+        assert_eq!(16, char.max_hp());
+        assert_eq!(16, char.current_hp);
+
+        while char.ring_level(&Ring::HP) < ring::MAX_LEVEL {
+            assert!(char.upgrade_ring(&Ring::HP));
+        }
+        assert!(!char.upgrade_ring(&Ring::HP));
+        assert_eq!(19, char.max_hp());
+
+        char.unequip_ring(&Key::Ring(Ring::HP));
+        assert_eq!(10, char.max_hp());
+        assert_eq!(10, char.current_hp);
+    }
+
+    #[test]
+    fn test_ring_sets() {
+        let mut player = new_char();
+        let mut enemy = new_char();
+
+        player.left_ring = Some(Ring::Counter);
+        player.right_ring = Some(Ring::Gold);
+        assert!(player.ring_sets().is_empty());
+
+        // flurry: the counter-attack is followed by a double beat
+        player.right_ring = Some(Ring::Double);
+        assert_eq!(vec![RingSet::Flurry], player.ring_sets());
+        player.maybe_counter_attack(&mut enemy);
+        assert_eq!(5, enemy.current_hp);
+
+        player.left_ring = Some(Ring::Chest);
+        player.right_ring = Some(Ring::Gold);
+        assert!(player.has_ring_set(RingSet::TreasureHunter));
+
+        // fire spells: magic attacks always burn, unless resisted with an amulet
+        let mut mage = Character::player();
+        mage.change_class("mage").unwrap_or_default();
+        mage.left_ring = Some(Ring::Fire);
+        mage.right_ring = Some(Ring::Magic);
+        assert_eq!(
+            Some((StatusEffect::Burn, 1)),
+            mage.inflicted_status_effect(&enemy)
+        );
+        enemy.equip(equipment::Equipment::amulet(50));
+        assert_eq!(
+            Some((StatusEffect::Burn, 2)),
+            mage.inflicted_status_effect(&enemy)
+        );
+        enemy.status_effect = Some(StatusEffect::Burn);
+        assert!(mage.inflicted_status_effect(&enemy).is_none());
+    }
+
     #[test]
     fn magic_attacks() {
         let mut player = Character::player();
@@ -1319,6 +1803,162 @@ mod tests {
         assert_eq!(300, player.gold_gained(4));
     }
 
+    #[test]
+    fn test_fatigue() {
+        let mut hero = new_plain_stats_char();
+        assert_eq!(10, hero.speed());
+
+        assert_eq!(40, hero.add_fatigue(40));
+        assert_eq!(10, hero.speed());
+
+        // above the threshold speed is reduced
+        assert_eq!(60, hero.add_fatigue(80));
+        assert_eq!(MAX_FATIGUE, hero.fatigue);
+        assert_eq!(5, hero.speed());
+
+        // rest recovers a fraction of fatigue, hp and mp
+        hero.current_hp = 2;
+        hero.current_mp = 0;
+        assert_eq!((5, 5, 50), hero.rest(0.5));
+        assert_eq!(50, hero.fatigue);
+        assert_eq!(7, hero.current_hp);
+        assert_eq!(5, hero.current_mp);
+        assert_eq!(10, hero.speed());
+
+        hero.restore();
+        assert_eq!(0, hero.fatigue);
+    }
+
+    #[test]
+    fn test_equipment_breaks() {
+        let mut player = new_char();
+        let mut enemy = new_char();
+        player.sword = Some(equipment::Equipment::sword(1).with_durability(2));
+        enemy.shield = Some(equipment::Equipment::shield(1).with_durability(1));
+
+        let _ = player.attack(&mut enemy);
+        assert_eq!(1, player.sword.as_ref().unwrap().durability());
+        assert!(enemy.shield.is_none());
+
+        let _ = player.attack(&mut enemy);
+        assert!(player.sword.is_none());
+
+        // the other pieces never wear out
+        player.shield = Some(equipment::Equipment::shield(1).with_durability(1));
+        player.boots = Some(equipment::Equipment::boots(1).with_durability(1));
+        player.wear_equipment(Key::Boots);
+        assert!(player.shield.is_some());
+        assert!(player.boots.is_some());
+    }
+
+    #[test]
+    fn test_armor_slots() {
+        let mut hero = new_char();
+        let (hp, speed) = (hero.max_hp(), hero.speed());
+
+        let armor = equipment::Equipment::armor(1);
+        let armor_hp = armor.strength();
+        assert!(armor_hp > 0);
+        assert!(hero.equip(armor).is_none());
+        assert_eq!(hp + armor_hp, hero.max_hp());
+        assert_eq!(hp + armor_hp, hero.current_hp);
+
+        let boots = equipment::Equipment::boots(1);
+        let boots_speed = boots.strength();
+        hero.equip(boots);
+        assert_eq!(speed + boots_speed, hero.speed());
+
+        // replacing returns the previous piece
+        let previous = hero.equip(equipment::Equipment::armor(5)).unwrap();
+        assert_eq!(1, previous.level());
+        assert_eq!(2, hero.equipment().len());
+
+        // amulets make status effects less likely
+        let mut enemy = new_char();
+        enemy.left_ring = Some(Ring::Fire);
+        assert_eq!(
+            Some((StatusEffect::Burn, 3)),
+            enemy.inflicted_status_effect(&hero)
+        );
+        hero.equip(equipment::Equipment::amulet(50));
+        assert_eq!(
+            Some((StatusEffect::Burn, 6)),
+            enemy.inflicted_status_effect(&hero)
+        );
+    }
+
+    #[test]
+    fn test_weapon_affinity() {
+        let mut thief = Character::player();
+        thief.change_class("thief").unwrap_or_default();
+        let (attack, speed) = (thief.physical_attack(), thief.speed());
+
+        let dagger = equipment::Equipment::dagger(10);
+        let (dagger_attack, dagger_speed) = (dagger.strength(), dagger.speed());
+        thief.equip(dagger);
+        assert_eq!(
+            attack + dagger_attack + dagger_attack / 4,
+            thief.physical_attack()
+        );
+        assert_eq!(speed + dagger_speed + dagger_speed / 4, thief.speed());
+
+        // no affinity bonus for a different class
+        let mut warrior = Character::player();
+        let (attack, speed) = (warrior.physical_attack(), warrior.speed());
+        warrior.equip(equipment::Equipment::dagger(10));
+        assert_eq!(attack + dagger_attack, warrior.physical_attack());
+        assert_eq!(speed + dagger_speed, warrior.speed());
+
+        assert!(!warrior.strikes_first());
+        warrior.equip(equipment::Equipment::bow(10));
+        assert!(warrior.strikes_first());
+        assert_eq!(20, warrior.critical_ratio());
+        warrior.equip(equipment::Equipment::axe(10));
+        assert_eq!(10, warrior.critical_ratio());
+
+        // custom classes can have affinity with any weapon kind
+        let mut archer = Character::player();
+        archer.class.affinity = Some(Key::Bow);
+        let bow = equipment::Equipment::class_weapon(&archer.class, 10);
+        assert_eq!(Key::Bow, bow.key());
+        let bow_attack = bow.strength();
+        let attack = archer.physical_attack();
+        archer.equip(bow);
+        assert_eq!(
+            attack + bow_attack + bow_attack / 4,
+            archer.physical_attack()
+        );
+    }
+
+    #[test]
+    fn test_affixes() {
+        let mut player = new_char();
+        let mut enemy = new_char();
+        let (speed, gold) = (player.speed(), player.gold_gained(1));
+
+        let affixes = vec![Affix::Swift, Affix::Lucky, Affix::Vampiric];
+        let sword = equipment::Equipment::sword(1).with_affixes(equipment::Rarity::Rare, affixes);
+        let sword_strength = sword.strength();
+        player.equip(sword);
+        assert_eq!(speed + speed / 10, player.speed());
+        assert_eq!(gold + gold / 4, player.gold_gained(1));
+
+        // vampiric recovers 10% of the damage
+        player.current_hp = 10;
+        let _ = player.attack(&mut enemy);
+        assert_eq!(25 - 10 - sword_strength, enemy.current_hp);
+        assert_eq!(11, player.current_hp);
+
+        assert!(player.inflicted_status_effect(&enemy).is_none());
+        let armor = equipment::Equipment::armor(1)
+            .with_affixes(equipment::Rarity::Magic, vec![Affix::Fire]);
+        player.equip(armor);
+        assert_eq!(
+            Some((StatusEffect::Burn, 4)),
+            player.inflicted_status_effect(&enemy)
+        );
+    }
+
     // HELPERS
 
     fn new_char() -> Character {
@@ -1331,6 +1971,8 @@ mod tests {
                 strength: Stat(10, 3),
                 speed: Stat(10, 2),
                 inflicts: None,
+                lockpick: false,
+                affinity: None,
             },
             1,
         )
@@ -1,3 +1,5 @@
+use crate::bank;
+use crate::bank::DeathPenalty;
 use crate::character;
 use crate::character::enemy;
 use crate::game::Game;
@@ -5,6 +7,7 @@ use crate::item;
 use crate::item::key::Key;
 use crate::location::Location;
 use crate::log;
+use crate::scan;
 use anyhow::{anyhow, bail, Result};
 
 use clap::Parser;
@@ -41,19 +44,121 @@ pub enum Command {
     #[command(name = "ls", display_order = 1)]
     Inspect,
 
+    /// Rest at the current location to recover some hp, mp and fatigue.
+    /// The further from home, the more likely to be ambushed by an enemy.
+    #[command(display_order = 1)]
+    Camp,
+
     /// Buys an item from the shop.
     /// If name is omitted lists the items available for sale.
     #[command(alias = "b", display_order = 2)]
     Buy { items: Vec<String> },
 
+    /// Sells items from the inventory to the shop.
+    /// If name is omitted lists the price paid for each item in the inventory.
+    #[command(display_order = 2)]
+    Sell {
+        items: Vec<String>,
+
+        /// Sell all the items in the inventory except the given ones.
+        #[arg(long)]
+        all_except: bool,
+
+        /// Confirm selling rare rings.
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Crafts an item at home combining others from the inventory.
+    /// If name is omitted lists the known recipes.
+    #[command(display_order = 2)]
+    Craft {
+        /// Item to craft. For equipment, two equal pieces are merged; for rings,
+        /// the ring is infused into the equipped weapon.
+        item: Option<String>,
+
+        /// List the known recipes, highlighting the ones that can be crafted.
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Manages the stash at home, where items are kept safe when the hero dies.
+    /// If no action is given lists the stash contents.
+    #[command(display_order = 2)]
+    Stash {
+        #[command(subcommand)]
+        action: Option<StashAction>,
+    },
+
+    /// Manages the gold kept at home, which survives the hero's death.
+    /// If no action is given prints the bank balance.
+    #[command(display_order = 2)]
+    Bank {
+        #[command(subcommand)]
+        action: Option<BankAction>,
+    },
+
+    /// Shows how the hero stats change when wearing an item from the inventory,
+    /// compared to the equipped one or to another given item.
+    #[command(display_order = 3)]
+    Compare {
+        item: String,
+
+        /// Item to compare against, e.g. `rpg compare shield with shield`.
+        #[arg(num_args = 0..=2)]
+        other: Vec<String>,
+    },
+
+    /// Manages named sets of equipment and rings that can be worn at once.
+    /// If no action is given lists the saved loadouts.
+    #[command(display_order = 3)]
+    Loadout {
+        #[command(subcommand)]
+        action: Option<LoadoutAction>,
+    },
+
+    /// Repairs the hero's worn out equipment at home.
+    #[command(display_order = 2)]
+    Repair,
+
+    /// Pays to identify the unknown items in the inventory at home.
+    #[command(display_order = 2)]
+    Identify,
+
     /// Uses an item from the inventory.
     #[command(alias = "u", display_order = 3)]
     Use { items: Vec<String> },
 
+    /// Leaves items from the inventory in a chest at the current location.
+    /// They can be picked up later with `ls`.
+    #[command(display_order = 3)]
+    Drop {
+        item: String,
+
+        /// How many items to drop.
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+
+    /// Prints lifetime statistics, kept across the hero's deaths.
+    #[command(name = "stats")]
+    LifetimeStats {
+        /// Export the statistics as CSV.
+        #[arg(long, conflicts_with = "json")]
+        csv: bool,
+
+        /// Export the statistics as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Prints the quest todo list.
     #[command(alias = "t", display_order = 4)]
     Todo,
 
+    /// Prints the achievements list, shared across games.
+    Achievements,
+
     /// Resets the current game.
     Reset {
         /// Reset data files, losing cross-hero progress.
@@ -61,6 +166,10 @@ pub enum Command {
         hard: bool,
     },
 
+    /// Start over at level 1 with a permanent bonus and tougher enemies.
+    /// Only available after beating the final boss.
+    Prestige,
+
     /// Change the character class.
     /// If name is omitted lists the available character classes.
     Class { name: Option<String> },
@@ -84,6 +193,51 @@ pub enum Command {
     Idkfa { level: i32 },
 }
 
+#[derive(clap::Subcommand)]
+pub enum StashAction {
+    /// Moves items from the inventory to the stash.
+    Put { items: Vec<String> },
+
+    /// Moves items from the stash back to the inventory.
+    Take { items: Vec<String> },
+
+    /// Lists the stash contents.
+    List,
+
+    /// Pays gold to add more slots to the stash.
+    Expand,
+}
+
+#[derive(clap::Subcommand)]
+pub enum BankAction {
+    /// Deposits gold in the bank, paying a fee. Deposits all if amount is omitted.
+    Deposit { amount: Option<i32> },
+
+    /// Withdraws gold from the bank. Withdraws all if amount is omitted.
+    Withdraw { amount: Option<i32> },
+
+    /// Sets how much gold is dropped in the tombstone when the hero dies.
+    Penalty {
+        #[arg(value_enum)]
+        penalty: DeathPenalty,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum LoadoutAction {
+    /// Saves the currently worn equipment and rings with the given name.
+    Save { name: String },
+
+    /// Wears the equipment and rings of the given loadout.
+    Use { name: String },
+
+    /// Forgets the given loadout.
+    Delete { name: String },
+
+    /// Lists the saved loadouts.
+    List,
+}
+
 pub fn run(cmd: Option<Command>, game: &mut Game) -> Result<()> {
     match cmd.unwrap_or(Command::Stat { items: vec![] }) {
         Command::Stat { items } => stat(game, &items)?,
@@ -96,10 +250,47 @@ pub fn run(cmd: Option<Command>, game: &mut Game) -> Result<()> {
         Command::Inspect => game.inspect(),
         Command::Class { name } => class(game, &name)?,
         Command::Battle { run, bribe } => battle(game, run, bribe)?,
+        Command::Camp => camp(game)?,
         Command::PrintWorkDir => println!("{}", game.location.path_string()),
         Command::Reset { .. } => game.reset(),
+        Command::Prestige => game.prestige()?,
         Command::Buy { items } => shop(game, &items)?,
+        Command::Sell {
+            items,
+            all_except,
+            yes,
+        } => sell(game, &items, all_except, yes)?,
+        Command::Craft { item, list } => craft(game, &item, list)?,
+        Command::Stash { action } => stash(game, action)?,
+        Command::Bank { action } => match action {
+            Some(BankAction::Deposit { amount }) => bank::deposit(game, amount)?,
+            Some(BankAction::Withdraw { amount }) => bank::withdraw(game, amount)?,
+            Some(BankAction::Penalty { penalty }) => bank::set_penalty(game, penalty)?,
+            None => bank::status(game)?,
+        },
+        Command::Compare { item, other } => compare(game, &item, &other)?,
+        Command::Loadout { action } => match action {
+            Some(LoadoutAction::Save { name }) => item::loadout::save(game, &name)?,
+            Some(LoadoutAction::Use { name }) => item::loadout::wear(game, &name)?,
+            Some(LoadoutAction::Delete { name }) => item::loadout::delete(game, &name)?,
+            Some(LoadoutAction::List) | None => item::loadout::list(game),
+        },
+        Command::Repair => item::shop::repair(game)?,
+        Command::Identify => item::curse::identify(game)?,
         Command::Use { items } => use_item(game, &items)?,
+        Command::Drop { item, count } => game.drop_item(&Key::from(&item)?, count)?,
+        Command::LifetimeStats { csv, json } => {
+            if csv {
+                print!("{}", game.stats.to_csv());
+            } else if json {
+                println!("{}", game.stats.to_json());
+            } else {
+                log::lifetime_stats(&game.stats);
+            }
+        }
+        Command::Achievements => {
+            log::quest_list(game.achievements.list());
+        }
         Command::Todo => {
             log::quest_list(game.quests.list());
         }
@@ -132,7 +323,8 @@ fn change_dir(game: &mut Game, dest: &str, run: bool, bribe: bool, force: bool)
 /// Potentially run a battle at the current location, independently from
 /// the hero's movement.
 fn battle(game: &mut Game, run: bool, bribe: bool) -> Result<()> {
-    if let Some(mut enemy) = enemy::spawn(&game.location, &game.player) {
+    let scan = scan::scan(game);
+    if let Some(mut enemy) = enemy::spawn(&game.location, &game.player, &scan) {
         if let Err(character::Dead) = game.battle(&mut enemy, run, bribe) {
             game.reset();
             bail!("");
@@ -141,6 +333,61 @@ fn battle(game: &mut Game, run: bool, bribe: bool) -> Result<()> {
     Ok(())
 }
 
+/// Rest at the current location, possibly being ambushed by an enemy.
+fn camp(game: &mut Game) -> Result<()> {
+    if let Err(character::Dead) = game.camp() {
+        game.reset();
+        bail!("");
+    }
+    Ok(())
+}
+
+fn sell(game: &mut Game, items: &[String], all_except: bool, confirmed: bool) -> Result<()> {
+    // parse items and break if any is invalid/unknown
+    let mut keys = Vec::new();
+    for item in items {
+        keys.push(Key::from(item)?);
+    }
+
+    if all_except {
+        item::shop::sell_all_except(game, &keys, confirmed)
+    } else if keys.is_empty() {
+        item::shop::sell_list(game)
+    } else {
+        item::shop::sell(game, &keys, confirmed)
+    }
+}
+
+fn craft(game: &mut Game, item: &Option<String>, list: bool) -> Result<()> {
+    match item {
+        Some(item) if !list => item::craft::craft(game, &Key::from(item)?),
+        _ => item::craft::list(game),
+    }
+}
+
+fn stash(game: &mut Game, action: Option<StashAction>) -> Result<()> {
+    match action {
+        Some(StashAction::Put { items }) => item::stash::put(game, &parse_keys(&items)?),
+        Some(StashAction::Take { items }) => item::stash::take(game, &parse_keys(&items)?),
+        Some(StashAction::Expand) => item::stash::expand(game),
+        Some(StashAction::List) | None => item::stash::list(game),
+    }
+}
+
+fn parse_keys(items: &[String]) -> Result<Vec<Key>> {
+    items.iter().map(|item| Key::from(item)).collect()
+}
+
+fn compare(game: &Game, item: &str, other: &[String]) -> Result<()> {
+    // allow both `compare a b` and `compare a with b`
+    let other = match other {
+        [] => None,
+        [other] | [_, other] => Some(Key::from(other)?),
+        _ => bail!("too many items to compare."),
+    };
+    item::loadout::compare(game, &Key::from(item)?, other.as_ref())
+}
+
 /// Set the class for the player character
 fn class(game: &mut Game, class_name: &Option<String>) -> Result<()> {
     if !game.location.is_home() {
@@ -164,10 +411,18 @@ fn class(game: &mut Game, class_name: &Option<String>) -> Result<()> {
 }
 
 /// Buy an item from the shop or list the available items if no item name is provided.
-/// Shopping is only allowed when the player is at the home directory.
+/// Shopping is only allowed when the player is at the home directory, or
+/// where the travelling merchant is.
 fn shop(game: &mut Game, items: &[String]) -> Result<()> {
+    // away from home, buy from the travelling merchant if it's there
+    let merchant = !game.location.is_home() && item::merchant::is_here(game);
+
     if items.is_empty() {
-        item::shop::list(game)
+        if merchant {
+            item::merchant::list(game)
+        } else {
+            item::shop::list(game)
+        }
     } else {
         // parse items and break if any is invalid/unknown
         let mut keys = Vec::new();
@@ -175,7 +430,11 @@ fn shop(game: &mut Game, items: &[String]) -> Result<()> {
             keys.push(Key::from(item)?);
         }
 
-        item::shop::buy(game, &keys)
+        if merchant {
+            item::merchant::buy(game, &keys)
+        } else {
+            item::shop::buy(game, &keys)
+        }
     }
 }
 
@@ -200,12 +459,32 @@ fn use_item(game: &mut Game, items: &[String]) -> Result<()> {
     } else {
         for item_name in items {
             let item_name = Key::from(item_name)?;
-            game.use_item(item_name)?
+            if item_name == Key::Bomb {
+                throw_bomb(game)?
+            } else {
+                game.use_item(item_name)?
+            }
         }
     }
     Ok(())
 }
 
+/// Throw a bomb at an enemy in the current location, starting a battle.
+fn throw_bomb(game: &mut Game) -> Result<()> {
+    if !game.inventory().contains_key(&Key::Bomb) {
+        bail!("item not found.");
+    }
+    let scan = scan::scan(game);
+    let Some(mut enemy) = enemy::provoke(&game.location, &game.player, &scan) else {
+        bail!("no enemy around to throw the bomb at.");
+    };
+    if let Err(character::Dead) = game.bomb_battle(&mut enemy) {
+        game.reset();
+        bail!("");
+    }
+    Ok(())
+}
+
 fn debug_command(game: &mut Game, level: i32) {
     game.reset();
     game.gold = 5000 * level;
@@ -1,5 +1,9 @@
+use crate::achievement::Achievements;
 use crate::character::class;
+use crate::character::enemy;
 use crate::game;
+use crate::item::craft;
+use crate::item::shop;
 use anyhow::{bail, Result};
 use std::{fs, io, path};
 
@@ -23,6 +27,20 @@ pub fn save(game: &game::Game) -> Result<(), io::Error> {
     write(data_file(), data)
 }
 
+/// Load the achievements file, defaulting to an empty one if it doesn't
+/// exist or can't be parsed.
+pub fn load_achievements() -> Achievements {
+    read(achievements_file())
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_achievements(achievements: &Achievements) -> Result<(), io::Error> {
+    let data = serde_json::to_vec(achievements).unwrap();
+    write(achievements_file(), data)
+}
+
 pub fn remove() {
     let rpg_dir = rpg_dir();
     if rpg_dir.exists() {
@@ -36,6 +54,24 @@ pub fn load_classes() {
     }
 }
 
+pub fn load_recipes() {
+    if let Ok(bytes) = read(recipes_file()) {
+        craft::load(&bytes)
+    }
+}
+
+pub fn load_lairs() {
+    if let Ok(bytes) = read(lairs_file()) {
+        enemy::load(&bytes)
+    }
+}
+
+pub fn load_shop() {
+    if let Ok(bytes) = read(shop_file()) {
+        shop::load(&bytes)
+    }
+}
+
 fn read(file: path::PathBuf) -> Result<Vec<u8>, NotFound> {
     fs::read(file).map_err(|_| NotFound)
 }
@@ -63,10 +99,26 @@ fn data_file() -> path::PathBuf {
     rpg_dir().join("data")
 }
 
+fn achievements_file() -> path::PathBuf {
+    rpg_dir().join("achievements")
+}
+
 fn classes_file() -> path::PathBuf {
     rpg_dir().join("classes.yaml")
 }
 
+fn recipes_file() -> path::PathBuf {
+    rpg_dir().join("recipes.yaml")
+}
+
+fn lairs_file() -> path::PathBuf {
+    rpg_dir().join("lairs.yaml")
+}
+
+fn shop_file() -> path::PathBuf {
+    rpg_dir().join("shop.yaml")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,331 @@
+use crate::game::Game;
+use crate::log;
+use crate::quest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Highest difficulty tier of a dungeon.
+const MAX_TIER: i32 = 5;
+
+/// Enemy levels added for each tier above the first.
+const TIER_LEVELS: i32 = 2;
+
+/// Battles to win per tier to clear a dungeon.
+const CLEAR_BATTLES: i32 = 3;
+
+/// Pack indexes, loose objects and branches read at most from a repository.
+const MAX_PACKS: usize = 100;
+const MAX_LOOSE_OBJECTS: usize = 10_000;
+const MAX_BRANCHES: usize = 1_000;
+
+/// Directories read at most when looking for repositories, e.g. for
+/// the clear every repo quest.
+const MAX_REPOS_ENTRIES: usize = 1_000;
+
+/// A git repository, explored as a named dungeon. The hero is inside the
+/// dungeon at the repository directory and any of its subdirectories.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Repo {
+    /// Path of the directory that contains the `.git` entry.
+    pub root: String,
+    pub name: String,
+
+    /// Objects in the repository database, as a measure of the size of its
+    /// history. Unlike the reflog, it includes the history of clones.
+    #[serde(alias = "commits")]
+    pub objects: usize,
+    pub branches: usize,
+}
+
+/// Progress of the hero at a dungeon, preserved across hero's lifes.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Record {
+    pub battles_won: i32,
+    pub cleared: bool,
+}
+
+impl Repo {
+    /// Find the repository that contains the given path, if any. The home
+    /// directory and its parents are not considered, since dotfiles
+    /// repositories would turn every directory into a dungeon.
+    pub fn find(path: &Path) -> Option<Self> {
+        let home = dirs::home_dir();
+        path.ancestors()
+            .take_while(|dir| home.as_deref().is_none_or(|home| !home.starts_with(dir)))
+            .find_map(Self::read)
+    }
+
+    /// Read the repository at the given directory, if it has one.
+    fn read(root: &Path) -> Option<Self> {
+        let git_dir = common_dir(&git_dir(root)?);
+        Some(Self {
+            root: root.to_string_lossy().to_string(),
+            name: root.file_name()?.to_string_lossy().to_string(),
+            objects: count_objects(&git_dir),
+            branches: count_branches(&git_dir),
+        })
+    }
+
+    /// Difficulty tier of the dungeon, from 1 to 5, based on the amount
+    /// of objects and branches.
+    pub fn tier(&self) -> i32 {
+        let tier = match self.objects {
+            0..=99 => 1,
+            100..=999 => 2,
+            1_000..=9_999 => 3,
+            _ => 4,
+        };
+        let tier = if self.branches >= 10 { tier + 1 } else { tier };
+        tier.min(MAX_TIER)
+    }
+
+    /// Levels added to the enemies spawned inside the dungeon.
+    pub fn level_bonus(&self) -> i32 {
+        (self.tier() - 1) * TIER_LEVELS
+    }
+
+    fn battles_to_clear(&self) -> i32 {
+        self.tier() * CLEAR_BATTLES
+    }
+}
+
+/// Return the `.git` directory of the given one. Worktrees and submodules
+/// have a `.git` file pointing to it instead.
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let git = root.join(".git");
+    if git.is_dir() {
+        return Some(git);
+    }
+
+    let contents = fs::read_to_string(&git).ok()?;
+    let target = contents.strip_prefix("gitdir:")?.trim();
+    let target = root.join(target);
+    target.is_dir().then_some(target)
+}
+
+/// Return the directory shared by all the worktrees of a repository, where
+/// its objects and branches are kept.
+fn common_dir(git_dir: &Path) -> PathBuf {
+    fs::read_to_string(git_dir.join("commondir"))
+        .map(|common| git_dir.join(common.trim()))
+        .ok()
+        .filter(|common| common.is_dir())
+        .unwrap_or_else(|| git_dir.to_path_buf())
+}
+
+/// Count the objects in the pack indexes plus the loose ones.
+fn count_objects(git_dir: &Path) -> usize {
+    let objects = git_dir.join("objects");
+    let packed: usize = fs::read_dir(objects.join("pack"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+        .take(MAX_PACKS)
+        .map(|path| pack_objects(&path).unwrap_or(0))
+        .sum();
+
+    // loose objects are stored in directories named after their first byte
+    let loose = fs::read_dir(&objects)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .flat_map(|entry| fs::read_dir(entry.path()).into_iter().flatten().flatten())
+        .take(MAX_LOOSE_OBJECTS)
+        .count();
+
+    packed + loose
+}
+
+/// Read the amount of objects of a pack from its index, which is the last
+/// entry of its 256 entries fanout table. Version 2 indexes start with a
+/// header before the table.
+fn pack_objects(index: &Path) -> io::Result<usize> {
+    let mut file = fs::File::open(index)?;
+    let mut header = [0; 4];
+    file.read_exact(&mut header)?;
+    let table_start = if header == *b"\xfftOc" { 8 } else { 0 };
+
+    let mut count = [0; 4];
+    file.seek(SeekFrom::Start(table_start + 255 * 4))?;
+    file.read_exact(&mut count)?;
+    Ok(u32::from_be_bytes(count) as usize)
+}
+
+/// Count the branches in the refs directory and the packed refs file.
+fn count_branches(git_dir: &Path) -> usize {
+    let heads = git_dir.join("refs").join("heads");
+    let mut branches = HashSet::new();
+    let mut pending = vec![heads.clone()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            if branches.len() >= MAX_BRANCHES {
+                return branches.len();
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(name) = path.strip_prefix(&heads) {
+                branches.insert(name.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    let packed = fs::read_to_string(git_dir.join("packed-refs")).unwrap_or_default();
+    for line in packed.lines() {
+        if let Some((_, name)) = line.split_once(" refs/heads/") {
+            branches.insert(name.to_string());
+        }
+    }
+    branches.len().min(MAX_BRANCHES)
+}
+
+/// Return the roots of the repositories found at the given directory or
+/// directly under it.
+pub fn repos_under(dir: &Path) -> HashSet<String> {
+    let mut dirs = vec![dir.to_path_buf()];
+    dirs.extend(
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .take(MAX_REPOS_ENTRIES)
+            .map(|entry| entry.path()),
+    );
+    dirs.into_iter()
+        .filter(|dir| git_dir(dir).is_some())
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Return the dungeon at the hero's location, as of the last scan.
+pub fn current(game: &Game) -> Option<&Repo> {
+    game.scans
+        .get(&game.location.path_string())
+        .and_then(|scan| scan.repo.as_ref())
+}
+
+/// Count a battle won at the hero's location towards clearing its dungeon.
+pub fn battle_won(game: &mut Game) {
+    let Some(repo) = current(game).cloned() else {
+        return;
+    };
+
+    let record = game.dungeons.entry(repo.root.clone()).or_default();
+    if record.cleared {
+        return;
+    }
+    record.battles_won += 1;
+    if record.battles_won >= repo.battles_to_clear() {
+        record.cleared = true;
+        log::dungeon_cleared(&repo.name);
+        quest::dungeon_cleared(game, &repo.root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::scan;
+
+    /// Create a fake repository with the given amount of objects, all but
+    /// one of them in a pack, and one loose and one packed branch.
+    fn fake_repo(root: &Path, objects: usize) {
+        fs::create_dir_all(root.join(".git/objects/pack")).unwrap();
+        fs::create_dir_all(root.join(".git/objects/ab")).unwrap();
+        fs::create_dir_all(root.join(".git/refs/heads/feature")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join(".git/refs/heads/main"), "").unwrap();
+        fs::write(root.join(".git/refs/heads/feature/x"), "").unwrap();
+        fs::write(
+            root.join(".git/packed-refs"),
+            "# pack-refs\nabc123 refs/heads/main\nabc456 refs/heads/old\nabc789 refs/tags/v1\n",
+        )
+        .unwrap();
+        fs::write(root.join(".git/objects/ab/cdef"), "").unwrap();
+
+        let mut index = b"\xfftOc\0\0\0\x02".to_vec();
+        for _ in 0..256 {
+            index.extend((objects as u32 - 1).to_be_bytes());
+        }
+        fs::write(root.join(".git/objects/pack/pack-1.idx"), index).unwrap();
+        fs::write(root.join(".git/objects/pack/pack-1.pack"), "").unwrap();
+    }
+
+    #[test]
+    fn find_repo() {
+        let dir = std::env::temp_dir().join("rpg-dungeon-test");
+        let _ = fs::remove_dir_all(&dir);
+        fake_repo(&dir.join("game"), 150);
+
+        let repo = Repo::find(&dir.join("game").join("src")).unwrap();
+        assert_eq!("game", repo.name);
+        assert_eq!(150, repo.objects);
+        assert_eq!(3, repo.branches);
+        assert_eq!(2, repo.tier());
+        assert_eq!(2, repo.level_bonus());
+        assert!(Repo::find(&dir).is_none());
+
+        // version 1 indexes have no header
+        let mut index = Vec::new();
+        for _ in 0..256 {
+            index.extend(1000_u32.to_be_bytes());
+        }
+        fs::write(dir.join("game/.git/objects/pack/pack-2.idx"), index).unwrap();
+        let repo = Repo::find(&dir.join("game")).unwrap();
+        assert_eq!(1150, repo.objects);
+        assert_eq!(3, repo.tier());
+
+        // worktrees point to their git dir from a file, and it points to
+        // the one shared with the main worktree
+        fs::create_dir_all(dir.join("game/.git/worktrees/wt")).unwrap();
+        fs::write(dir.join("game/.git/worktrees/wt/commondir"), "../..\n").unwrap();
+        fs::create_dir_all(dir.join("worktree")).unwrap();
+        fs::write(
+            dir.join("worktree/.git"),
+            "gitdir: ../game/.git/worktrees/wt\n",
+        )
+        .unwrap();
+        let repo = Repo::find(&dir.join("worktree")).unwrap();
+        assert_eq!(1150, repo.objects);
+        assert_eq!(3, repo.branches);
+
+        let repos = repos_under(&dir);
+        assert_eq!(2, repos.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn clear_dungeon() {
+        let dir = std::env::temp_dir().join("rpg-clear-dungeon-test");
+        let _ = fs::remove_dir_all(&dir);
+        fake_repo(&dir, 1);
+
+        let mut game = Game::new();
+        game.location = Location::from(&dir.join("src").to_string_lossy()).unwrap();
+        let repo = scan::scan(&mut game).repo.unwrap();
+        assert_eq!(1, repo.tier());
+
+        for _ in 0..CLEAR_BATTLES - 1 {
+            battle_won(&mut game);
+        }
+        assert!(!game.dungeons[&repo.root].cleared);
+        battle_won(&mut game);
+        assert!(game.dungeons[&repo.root].cleared);
+
+        // the record survives death
+        game.reset();
+        assert!(game.dungeons[&repo.root].cleared);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
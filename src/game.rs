@@ -1,19 +1,40 @@
+use crate::achievement::Achievements;
+use crate::bank::Bank;
 use crate::character;
+use crate::character::class;
 use crate::character::enemy;
 use crate::character::Character;
+use crate::dungeon;
 use crate::item::chest::Chest;
+use crate::item::curse::{self, Curse};
 use crate::item::key::Key;
+use crate::item::loadout::Loadout;
+use crate::item::merchant::{self, Merchant};
 use crate::item::ring::Ring;
-use crate::item::Item;
+use crate::item::shop::Shop;
+use crate::item::stash::Stash;
+use crate::item::{ChestKey, Item};
 use crate::location::Location;
 use crate::log;
 use crate::quest;
 use crate::quest::QuestList;
 use crate::randomizer::random;
 use crate::randomizer::Randomizer;
+use crate::scan::{self, Scan};
+use crate::stats::Stats;
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Fatigue gained by the hero on each directory step.
+const STEP_FATIGUE: i32 = 2;
+
+/// Fatigue gained by the hero on each battle turn.
+const TURN_FATIGUE: i32 = 1;
+
+/// Fraction of hp, mp and fatigue recovered when camping.
+const CAMP_RECOVERY: f64 = 0.5;
 
 /// Carries all the game state that is saved between commands and exposes
 /// the high-level interface for gameplay: moving across directories and
@@ -35,23 +56,57 @@ pub struct Game {
     /// Chests left at the location where the player dies.
     pub tombstones: HashMap<String, Chest>,
 
+    /// Location of the most recent tombstone, used by warp scrolls.
+    last_tombstone: Option<Location>,
+
+    /// Items that didn't fit in the inventory, left at the location where
+    /// they were found.
+    pub chests: HashMap<String, Chest>,
+
+    /// Locked chests that haven't been opened yet, kept at the location
+    /// where they were found until the hero brings a key.
+    pub locked_chests: HashMap<String, Chest>,
+
+    /// Items kept at home, preserved across hero's lifes.
+    pub stash: Stash,
+
+    /// Gold kept at home, preserved across hero's lifes.
+    pub bank: Bank,
+
+    /// Stock and special offers of the shop, preserved across hero's lifes.
+    pub shop: Shop,
+
+    /// Travelling merchant currently selling away from home, if any.
+    pub merchant: Option<Merchant>,
+
+    /// Named sets of equipment and rings that can be worn at once.
+    pub loadouts: HashMap<String, Loadout>,
+
     /// There's one instance of each type of ring in the game.
     /// This set starts with all rings and they are moved to the inventory as
     /// they are found in chests.
     pub ring_pool: HashSet<Ring>,
 
     pub quests: QuestList,
+
+    /// Lifetime counters, preserved across hero's lifes.
+    pub stats: Stats,
+
+    /// Contents of the most recently visited directories, keyed by path.
+    pub scans: HashMap<String, Scan>,
+
+    /// Progress at each git repository dungeon, keyed by its root path.
+    pub dungeons: HashMap<String, dungeon::Record>,
+
+    /// Achievements are stored separately since they are shared across games.
+    #[serde(skip)]
+    pub achievements: Achievements,
 }
 
 impl Game {
     pub fn new() -> Self {
         let quests = QuestList::new();
-
-        // There's one instance of each ring exiting per game.
-        // The diamond ring is the only one that's found in the shop
-        // instead of chests
-        let mut ring_pool = Ring::set();
-        ring_pool.remove(&Ring::Diamond);
+        let ring_pool = Self::initial_ring_pool();
 
         Self {
             location: Location::home(),
@@ -59,23 +114,60 @@ impl Game {
             gold: 0,
             inventory: HashMap::new(),
             tombstones: HashMap::new(),
+            last_tombstone: None,
+            chests: HashMap::new(),
+            locked_chests: HashMap::new(),
+            stash: Stash::default(),
+            bank: Bank::default(),
+            shop: Shop::default(),
+            merchant: None,
+            loadouts: HashMap::new(),
             inspected: HashSet::new(),
             quests,
             ring_pool,
+            stats: Stats::default(),
+            scans: HashMap::new(),
+            dungeons: HashMap::new(),
+            achievements: Achievements::default(),
         }
     }
 
+    /// There's one instance of each ring exiting per game.
+    /// The diamond ring is the only one that's found in the shop
+    /// instead of chests
+    fn initial_ring_pool() -> HashSet<Ring> {
+        let mut ring_pool = Ring::set();
+        ring_pool.remove(&Ring::Diamond);
+        ring_pool
+    }
+
     /// Remove the game data and reset this reference.
     /// Progress is preserved across games.
     pub fn reset(&mut self) {
         let mut new_game = Self::new();
-        // preserve tombstones and quests across hero's lifes
+        // preserve tombstones, stash, bank, quests and stats across hero's lifes
         std::mem::swap(&mut new_game.tombstones, &mut self.tombstones);
+        std::mem::swap(&mut new_game.last_tombstone, &mut self.last_tombstone);
+        std::mem::swap(&mut new_game.chests, &mut self.chests);
+        std::mem::swap(&mut new_game.locked_chests, &mut self.locked_chests);
+        std::mem::swap(&mut new_game.stash, &mut self.stash);
+        std::mem::swap(&mut new_game.bank, &mut self.bank);
+        std::mem::swap(&mut new_game.shop, &mut self.shop);
+        std::mem::swap(&mut new_game.loadouts, &mut self.loadouts);
         std::mem::swap(&mut new_game.quests, &mut self.quests);
         std::mem::swap(&mut new_game.ring_pool, &mut self.ring_pool);
+        std::mem::swap(&mut new_game.stats, &mut self.stats);
+        std::mem::swap(&mut new_game.scans, &mut self.scans);
+        std::mem::swap(&mut new_game.dungeons, &mut self.dungeons);
+        std::mem::swap(&mut new_game.achievements, &mut self.achievements);
 
-        // remember last selected class
+        // remember last selected class and prestige tier
         new_game.player = character::Character::new(self.player.class.clone(), 1);
+        new_game.player.prestige = self.player.prestige;
+        new_game.player.ring_levels = std::mem::take(&mut self.player.ring_levels);
+        new_game.player.ring_curses = std::mem::take(&mut self.player.ring_curses);
+        new_game.player.unidentified_rings = std::mem::take(&mut self.player.unidentified_rings);
+        new_game.player.restore();
 
         // replace the current, finished game with the new one
         *self = new_game;
@@ -83,6 +175,31 @@ impl Game {
         quest::game_reset(self);
     }
 
+    /// Start over after beating the final boss: the hero goes back to level 1
+    /// with a permanent stat bonus, while enemies, rings and quests are
+    /// replenished with harder variants.
+    pub fn prestige(&mut self) -> Result<()> {
+        if !self.quests.gorthaur_beaten() {
+            bail!("Prestige is only available after beating gorthaur.");
+        }
+
+        let tier = self.player.prestige + 1;
+        self.reset();
+        self.player.prestige = tier;
+        self.player.restore();
+        // rings kept in the stash are not found again
+        let mut ring_pool = Self::initial_ring_pool();
+        ring_pool.retain(|ring| !self.stash.contains(&Key::Ring(ring.clone())));
+        self.ring_pool = ring_pool;
+        self.quests = QuestList::with_prestige(tier);
+        // dungeons are cleared again for the new quests
+        self.dungeons.clear();
+
+        log::prestige(&self.player);
+        quest::prestige(self, tier);
+        Ok(())
+    }
+
     /// Move the hero's location towards the given destination, one directory
     /// at a time, with some chance of enemies appearing on each one.
     pub fn go_to(
@@ -92,13 +209,26 @@ impl Game {
         bribe: bool,
     ) -> Result<(), character::Dead> {
         while self.location != *dest {
+            self.player.add_fatigue(STEP_FATIGUE);
             self.visit(self.location.go_to(dest))?;
 
-            if !self.location.is_home() {
-                if let Some(mut enemy) = enemy::spawn(&self.location, &self.player) {
-                    if self.battle(&mut enemy, run, bribe)? {
-                        return Ok(());
-                    }
+            if self.location.is_home() {
+                merchant::moved(self, false);
+                continue;
+            }
+
+            let scan = scan::scan(self);
+            if let Some(repo) = scan.repo.as_ref() {
+                if repo.root == self.location.path_string() {
+                    log::dungeon_entered(repo, self.dungeons.get(&repo.root));
+                }
+            }
+            let enemy = enemy::spawn(&self.location, &self.player, &scan);
+            // a merchant may show up instead of an enemy
+            merchant::moved(self, enemy.is_none());
+            if let Some(mut enemy) = enemy {
+                if self.battle(&mut enemy, run, bribe)? {
+                    return Ok(());
                 }
             }
         }
@@ -108,6 +238,12 @@ impl Game {
     /// Set the hero's location to the one given, and apply related side effects.
     pub fn visit(&mut self, location: Location) -> Result<(), character::Dead> {
         self.location = location;
+        // read the directory when entering it, so its dungeon is known
+        // even when moving without battles
+        if let Some(repo) = scan::scan(self).repo {
+            quest::dungeon_entered(self, &repo);
+        }
+        quest::location_visited(self);
         if self.location.is_home() {
             let (recovered_hp, recovered_mp, healed) = self.player.restore();
             log::heal(
@@ -124,11 +260,41 @@ impl Game {
 
         if let Err(character::Dead) = result {
             // drops tombstone
-            self.battle_lost();
+            let cause = self
+                .player
+                .status_effect
+                .map_or(String::from("ruling-rng"), |status| status.to_string());
+            self.battle_lost(&cause);
         }
         result
     }
 
+    /// Rest at the current location, recovering part of the hero's hp, mp
+    /// and fatigue. There's a chance, that grows with the distance from
+    /// home, of being ambushed by an enemy before getting any rest.
+    pub fn camp(&mut self) -> Result<(), character::Dead> {
+        if self.location.is_home() {
+            // no need to camp at home, just get the regular healing
+            return self.visit(self.location.clone());
+        }
+
+        let scan = scan::scan(self);
+        if let Some(mut enemy) = enemy::ambush(&self.location, &self.player, &scan) {
+            self.battle(&mut enemy, false, false)?;
+            return Ok(());
+        }
+
+        let (recovered_hp, recovered_mp, recovered_fatigue) = self.player.rest(CAMP_RECOVERY);
+        log::camp(
+            &self.player,
+            &self.location,
+            recovered_hp,
+            recovered_mp,
+            recovered_fatigue,
+        );
+        Ok(())
+    }
+
     /// Look for chests and tombstones at the current location.
     /// Remembers previously visited locations for consistency.
     pub fn inspect(&mut self) {
@@ -136,46 +302,216 @@ impl Game {
             let (items, gold) = chest.pick_up(self);
             log::tombstone(&items, gold);
             quest::tombstone(self);
+            self.leave_behind(chest);
+        }
+
+        if let Some(mut chest) = self.chests.remove(&self.location.to_string()) {
+            let (items, gold) = chest.pick_up(self);
+            log::chest(&items, gold);
+            self.leave_behind(chest);
         }
 
+        let mut found = false;
         if !self.inspected.contains(&self.location) {
             self.inspected.insert(self.location.clone());
             if let Some(mut chest) = Chest::generate(self) {
                 let (items, gold) = chest.pick_up(self);
                 log::chest(&items, gold);
                 quest::chest(self);
+                quest::gold_earned(self, gold);
+                self.leave_behind(chest);
+            }
+            if let Some(chest) = Chest::generate_locked(self) {
+                self.locked_chests.insert(self.location.to_string(), chest);
+                found = true;
             }
         }
+
+        self.open_locked_chest(found);
+    }
+
+    /// Open the locked chest at the current location, if any, by picking
+    /// the lock or using a key from the inventory. Thieves get a single try
+    /// at picking each lock, when the chest is first found. The chest is left
+    /// in place if it can't be opened.
+    fn open_locked_chest(&mut self, found: bool) {
+        let location = self.location.to_string();
+        if !self.locked_chests.contains_key(&location) {
+            return;
+        }
+
+        let opened_with = if found && self.player.picks_locks() && random().lockpick_succeeds() {
+            "lockpick"
+        } else if self.take_item(&Key::Chest).is_some() {
+            "key"
+        } else {
+            log::locked_chest();
+            return;
+        };
+
+        let mut chest = self.locked_chests.remove(&location).unwrap();
+        let (items, gold) = chest.pick_up(self);
+        log::unlocked_chest(opened_with, &items, gold);
+        quest::chest(self);
+        quest::gold_earned(self, gold);
+        self.leave_behind(chest);
+    }
+
+    /// Keep the chest at the current location if it still has items that
+    /// didn't fit in the inventory, so the hero can come back for them.
+    fn leave_behind(&mut self, chest: Chest) {
+        if !chest.is_empty() {
+            log::inventory_full();
+            self.chests
+                .entry(self.location.to_string())
+                .or_default()
+                .extend(chest);
+        }
+    }
+
+    /// Return the location of the last tombstone, if it wasn't picked up yet.
+    pub fn last_tombstone(&self) -> Option<Location> {
+        self.last_tombstone
+            .clone()
+            .filter(|location| self.tombstones.contains_key(&location.to_string()))
+    }
+
+    /// Leave up to the given amount of items of the given kind in a chest
+    /// at the current location, so they can be picked up later.
+    pub fn drop_item(&mut self, key: &Key, count: usize) -> Result<()> {
+        if count == 0 {
+            bail!("at least one item must be dropped.");
+        }
+        let Some(items) = self.inventory.get_mut(key) else {
+            bail!("{} not found in the inventory.", key);
+        };
+
+        let dropped = items.len().min(count);
+        let removed = items.split_off(items.len() - dropped);
+        if items.is_empty() {
+            self.inventory.remove(key);
+        }
+        let chest = self.chests.entry(self.location.to_string()).or_default();
+        for item in removed.into_iter().rev() {
+            chest.add(item);
+        }
+
+        log::drop_items(key, dropped);
+        Ok(())
+    }
+
+    /// The total weight of the items in the inventory.
+    pub fn inventory_weight(&self) -> i32 {
+        self.inventory
+            .values()
+            .flatten()
+            .map(|item| item.weight())
+            .sum()
+    }
+
+    /// Return true if an item of the given weight fits in the inventory.
+    pub fn has_room(&self, weight: i32) -> bool {
+        self.inventory_weight() + weight <= self.player.carry_capacity()
+    }
+
+    /// Add the item to the inventory if there's room for it, otherwise
+    /// leave it in a chest at the current location.
+    pub fn add_or_leave(&mut self, item: Box<dyn Item>) {
+        if self.has_room(item.weight()) {
+            self.add_item(item);
+        } else {
+            let mut chest = Chest::default();
+            chest.add(item);
+            self.leave_behind(chest);
+        }
     }
 
     pub fn add_item(&mut self, item: Box<dyn Item>) {
+        // a duplicate ring is used to upgrade the one already owned
+        if let Key::Ring(ring) = item.key() {
+            let owned = self.player.wears(ring.clone()) || self.inventory.contains_key(&item.key());
+            if owned && self.player.upgrade_ring(&ring) {
+                log::ring_upgraded(&self.player, &ring);
+                return;
+            }
+        }
+
         let key = item.key();
         let entry = self.inventory.entry(item.key()).or_default();
         entry.push(item);
         quest::item_added(self, key);
     }
 
-    pub fn use_item(&mut self, name: Key) -> Result<()> {
-        // get all items of that type and use one
-        // if there are no remaining, drop the type from the inventory
-        if let Some(mut items) = self.inventory.remove(&name) {
-            if let Some(mut item) = items.pop() {
-                item.apply(self);
-                quest::item_used(self, item.key());
-            }
+    /// Remove one item of the given type from the inventory, if any.
+    /// If there are no remaining, drop the type from the inventory.
+    pub fn take_item(&mut self, name: &Key) -> Option<Box<dyn Item>> {
+        let items = self.inventory.get_mut(name)?;
+        let item = items.pop();
+        if items.is_empty() {
+            self.inventory.remove(name);
+        }
+        item
+    }
+
+    /// Return false for items whose properties aren't known yet.
+    pub fn is_identified(&self, item: &dyn Item) -> bool {
+        match item.key() {
+            Key::Ring(ring) => !self.player.unidentified_rings.contains(&ring),
+            _ => item
+                .as_equipment()
+                .is_none_or(|piece| piece.is_identified()),
+        }
+    }
 
-            if !items.is_empty() {
-                self.inventory.insert(name, items);
+    /// The name of the kind of item shown to the player, hiding it if not
+    /// identified yet, e.g. `???-rng`.
+    pub fn item_name(&self, item: &dyn Item) -> String {
+        if self.is_identified(item) {
+            item.key().to_string()
+        } else {
+            curse::unknown_name(item)
+        }
+    }
+
+    /// Identify all the items in the inventory.
+    /// Return the identified items with their curses, if any.
+    pub fn identify_all(&mut self) -> Vec<(String, Option<Curse>)> {
+        let mut identified = Vec::new();
+        for item in self.inventory.values_mut().flatten() {
+            if let Key::Ring(ring) = item.key() {
+                if self.player.unidentified_rings.remove(&ring) {
+                    let curse = self.player.ring_curses.get(&ring).copied();
+                    identified.push((ring.to_string(), curse));
+                }
+            } else if let Some(piece) = item.as_equipment().filter(|p| !p.is_identified()) {
+                let mut piece = piece.clone();
+                piece.identify();
+                identified.push((piece.to_string(), piece.curse()));
+                *item = Box::new(piece);
             }
+        }
+        identified
+    }
 
+    pub fn use_item(&mut self, name: Key) -> Result<()> {
+        if let Some(mut item) = self.take_item(&name) {
+            if let Err(error) = item.check_use(self) {
+                // put it back without counting it as a new item
+                self.inventory.entry(name).or_default().push(item);
+                return Err(error);
+            }
+            item.apply(self);
+            quest::item_used(self, item.key());
             Ok(())
+        } else if matches!(&name, Key::Ring(ring) if self.player.wears_cursed(ring)) {
+            bail!("{} is cursed and can't be removed.", name)
         } else if let Some(ring) = self.player.unequip_ring(&name) {
             // Rings are a special case of item in that they can be "used" while being
             // equipped, that is, while not being in the inventory.
             // The effect of using them is unequipping them.
             // This bit of complexity enables a cleaner command api.
             quest::item_used(self, ring.key());
-            self.add_item(Box::new(ring));
+            self.add_or_leave(Box::new(ring));
             Ok(())
         } else {
             bail!("item not found.")
@@ -191,23 +527,25 @@ impl Game {
 
     pub fn describe(&self, key: Key) -> Result<(String, String)> {
         let (display, description) = match key {
-            Key::Sword if self.player.sword.is_some() => self
-                .player
-                .sword
-                .as_ref()
-                .map(|s| (s.to_string(), s.describe()))
-                .unwrap(),
-            Key::Shield if self.player.shield.is_some() => self
+            _ if self.player.equipped(&key).is_some() => self
                 .player
-                .shield
-                .as_ref()
-                .map(|s| (s.to_string(), s.describe()))
+                .equipped(&key)
+                .map(|e| (e.to_string(), e.describe()))
                 .unwrap(),
-            Key::Ring(ref ring) if self.player.left_ring.as_ref() == Some(ring) => {
-                (ring.to_string(), ring.describe())
+            Key::Ring(ref ring)
+                if self.player.unidentified_rings.contains(ring)
+                    && self.inventory.contains_key(&key) =>
+            {
+                let item = self.inventory.get(&key).unwrap().first().unwrap();
+                (
+                    curse::unknown_name(item.as_ref()),
+                    String::from("unidentified"),
+                )
             }
-            Key::Ring(ref ring) if self.player.right_ring.as_ref() == Some(ring) => {
-                (ring.to_string(), ring.describe())
+            Key::Ring(ref ring)
+                if self.player.wears(ring.clone()) || self.inventory.contains_key(&key) =>
+            {
+                (ring.to_string(), self.player.describe_ring(ring))
             }
             _ => {
                 if let Some(items) = self.inventory.get(&key) {
@@ -238,27 +576,49 @@ impl Game {
             if self.gold >= bribe_cost && random().bribe_succeeds() {
                 self.gold -= bribe_cost;
                 log::bribe(&self.player, bribe_cost);
+                quest::bribed(self, bribe_cost);
                 return Ok(false);
             };
             log::bribe(&self.player, 0);
         } else if run {
-            let success = random().run_away_succeeds(
-                self.player.level,
-                enemy.level,
-                self.player.speed(),
-                enemy.speed(),
-            );
+            // smoke bombs guarantee a successful escape
+            let smoke_bomb = self.take_item(&Key::SmokeBomb).is_some();
+            if smoke_bomb {
+                quest::item_used(self, Key::SmokeBomb);
+            }
+            let success = smoke_bomb
+                || random().run_away_succeeds(
+                    self.player.level,
+                    enemy.level,
+                    self.player.speed(),
+                    enemy.speed(),
+                );
             log::run_away(&self.player, success);
             if success {
+                quest::ran_away(self);
                 return Ok(false);
             }
         }
 
-        if let Ok(xp) = self.run_battle(enemy) {
+        self.fight(enemy, false)
+    }
+
+    /// Start a battle by throwing a bomb at the enemy in the first turn.
+    /// Return Err<Dead> if the character dies.
+    pub fn bomb_battle(&mut self, enemy: &mut Character) -> Result<(), character::Dead> {
+        self.fight(enemy, true).map(|_| ())
+    }
+
+    fn fight(&mut self, enemy: &mut Character, bomb: bool) -> Result<bool, character::Dead> {
+        if let Ok((xp, damage_taken)) = self.run_battle(enemy, bomb) {
+            if damage_taken == 0 {
+                quest::flawless_victory(self);
+            }
             self.battle_won(enemy, xp);
             Ok(true)
         } else {
-            self.battle_lost();
+            quest::battle_lost(self, enemy);
+            self.battle_lost(&enemy.name());
             Err(character::Dead)
         }
     }
@@ -270,24 +630,48 @@ impl Game {
     /// Some special abilities are enabled by the player's equipped rings:
     /// Double-beat, counter-attack and revive.
     ///
-    /// Returns Ok(xp gained) if the player wins, or Err(()) if it loses.
-    fn run_battle(&mut self, enemy: &mut Character) -> Result<i32, character::Dead> {
+    /// If `bomb` is set, a bomb is thrown at the enemy in the player's first turn.
+    ///
+    /// Returns Ok((xp gained, damage taken)) if the player wins, or Err(()) if it
+    /// loses. The damage taken doesn't discount what was healed back with potions
+    /// or revived.
+    fn run_battle(
+        &mut self,
+        enemy: &mut Character,
+        mut bomb: bool,
+    ) -> Result<(i32, i32), character::Dead> {
         // Player's using the revive ring can come back to life at most once per battle
         let mut already_revived = false;
 
         // These accumulators get increased based on the character's speed:
         // the faster will get more frequent turns.
-        let (mut pl_accum, mut en_accum) = (0, 0);
+        // Bows grant the player the first turn.
+        let (mut pl_accum, mut en_accum) = if self.player.strikes_first() {
+            (enemy.speed(), 0)
+        } else {
+            (0, 0)
+        };
         let mut xp = 0;
+        let mut damage_taken = 0;
 
         while enemy.current_hp > 0 {
             pl_accum += self.player.speed();
             en_accum += enemy.speed();
 
             if pl_accum >= en_accum {
+                self.player.add_fatigue(TURN_FATIGUE);
+
                 // In some urgent circumstances, it's preferable to use the turn to
                 // recover mp or hp than attacking
-                if !self.autopotion(enemy) && !self.autoether(enemy) {
+                let thrown = if bomb {
+                    bomb = false;
+                    self.throw_bomb(enemy)
+                } else {
+                    self.autobomb(enemy)
+                };
+                if let Some(new_xp) = thrown {
+                    xp += new_xp;
+                } else if !self.autopotion(enemy) && !self.autoether(enemy) {
                     let (new_xp, _) = self.player.attack(enemy);
                     xp += new_xp;
 
@@ -296,12 +680,16 @@ impl Game {
 
                 // Status effects are applied after each turn. The player may die
                 // during its own turn because of status ailment damage
+                let hp = self.player.current_hp;
                 let died = self.player.apply_status_effects();
+                damage_taken += (hp - self.player.current_hp).max(0);
                 already_revived = self.player.maybe_revive(died, already_revived)?;
 
                 pl_accum = -1;
             } else {
+                let hp = self.player.current_hp;
                 let (_, died) = enemy.attack(&mut self.player);
+                damage_taken += (hp - self.player.current_hp).max(0);
                 already_revived = self.player.maybe_revive(died, already_revived)?;
 
                 self.player.maybe_counter_attack(enemy);
@@ -312,7 +700,7 @@ impl Game {
             }
         }
 
-        Ok(xp)
+        Ok((xp, damage_taken))
     }
 
     fn battle_won(&mut self, enemy: &Character, xp: i32) {
@@ -320,14 +708,44 @@ impl Game {
         self.gold += gold;
         let levels_up = self.player.add_experience(xp);
 
-        let reward_items =
-            Chest::battle_loot(self).map_or(HashMap::new(), |mut chest| chest.pick_up(self).0);
+        let mut reward_items = HashMap::new();
+        let mut leftover = Chest::default();
+        if let Some(mut chest) = Chest::battle_loot(self) {
+            reward_items = chest.pick_up(self).0;
+            leftover = chest;
+        }
+
+        // stronger enemies carry keys to locked chests
+        if matches!(
+            enemy.class.category,
+            class::Category::Rare | class::Category::Legendary
+        ) {
+            if self.has_room(1) {
+                self.add_item(Box::new(ChestKey::new()));
+                *reward_items.entry(Key::Chest.to_string()).or_insert(0) += 1;
+            } else {
+                leftover.add(Box::new(ChestKey::new()));
+            }
+        }
+        self.leave_behind(leftover);
 
         log::battle_won(self, xp, levels_up, gold, &reward_items);
+        quest::gold_earned(self, gold);
+
+        self.shop.battle_won();
+        dungeon::battle_won(self);
+        let interest = self.bank.battle_won();
+        if interest > 0 {
+            log::bank_interest(interest, self.bank.balance);
+            quest::gold_earned(self, interest);
+        }
         quest::battle_won(self, enemy, levels_up);
     }
 
-    fn battle_lost(&mut self) {
+    /// Handle the hero's death by the given cause, e.g. an enemy or a status effect.
+    fn battle_lost(&mut self, cause: &str) {
+        quest::died(self, cause);
+
         // Drop hero items in the location. If there was a previous tombstone
         // merge the contents of both chests
         let mut tombstone = Chest::drop(self);
@@ -336,10 +754,40 @@ impl Game {
             tombstone.extend(previous);
         }
         self.tombstones.insert(location, tombstone);
+        self.last_tombstone = Some(self.location.clone());
 
         log::battle_lost(&self.player);
     }
 
+    /// If the enemy is tougher than the player and there's a bomb available,
+    /// throw it instead of attacking in the current turn.
+    /// Return the xp gained if a bomb was thrown.
+    fn autobomb(&mut self, enemy: &mut Character) -> Option<i32> {
+        if enemy.level <= self.player.level || !self.inventory.contains_key(&Key::Bomb) {
+            return None;
+        }
+
+        // If there's a good chance of winning the battle on the next attack,
+        // don't waste the bomb.
+        let (potential_damage, _) = self.player.damage(enemy);
+        if potential_damage >= enemy.current_hp {
+            return None;
+        }
+
+        self.throw_bomb(enemy)
+    }
+
+    /// Throw a bomb from the inventory at the enemy.
+    /// Return the xp gained if there was a bomb to throw.
+    fn throw_bomb(&mut self, enemy: &mut Character) -> Option<i32> {
+        let bomb = self.take_item(&Key::Bomb)?;
+        let xp = self
+            .player
+            .throw(enemy, bomb.throw_damage().unwrap_or_default());
+        quest::item_used(self, Key::Bomb);
+        Some(xp)
+    }
+
     /// If the player is low on hp and has a potion available use it
     /// instead of attacking in the current turn.
     fn autopotion(&mut self, enemy: &Character) -> bool {
@@ -373,6 +821,13 @@ impl Game {
     }
 }
 
+/// Return the current unix timestamp in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
 impl Default for Game {
     fn default() -> Self {
         Self::new()
@@ -482,6 +937,25 @@ mod tests {
         assert_eq!(base_hp, game.player.max_hp());
     }
 
+    #[test]
+    fn test_ring_duplicate() {
+        let mut game = Game::new();
+        game.add_item(Box::new(Ring::Speed));
+        game.add_item(Box::new(Ring::Speed));
+        assert_eq!(1, *game.inventory().get(&Key::Ring(Ring::Speed)).unwrap());
+        assert_eq!(2, game.player.ring_level(&Ring::Speed));
+
+        // also when equipped
+        game.use_item(Key::Ring(Ring::Speed)).unwrap();
+        game.add_item(Box::new(Ring::Speed));
+        assert!(!game.inventory().contains_key(&Key::Ring(Ring::Speed)));
+        assert_eq!(3, game.player.ring_level(&Ring::Speed));
+
+        // ring levels survive the hero's death
+        game.reset();
+        assert_eq!(3, game.player.ring_level(&Ring::Speed));
+    }
+
     #[test]
     fn battle_won() {
         let enemy_base = class::Class::random(class::Category::Common);
@@ -527,6 +1001,87 @@ mod tests {
         assert_eq!(300, game.gold);
     }
 
+    #[test]
+    fn flawless_victory() {
+        let enemy_base = class::Class::random(class::Category::Common);
+        let enemy_class = class::Class {
+            speed: class::Stat(1, 1),
+            hp: class::Stat(105, 1),
+            strength: class::Stat(14, 1),
+            ..enemy_base.clone()
+        };
+        let mut enemy = character::Character::new(enemy_class, 1);
+
+        let mut game = Game::new();
+        let player_class = class::Class {
+            speed: class::Stat(10, 1),
+            hp: class::Stat(20, 1),
+            strength: class::Stat(10, 1),
+            ..game.player.class.clone()
+        };
+        game.player = character::Character::new(player_class, 1);
+        game.add_item(Box::new(item::Potion::new(10)));
+
+        // the hero attacks 9 times, gets hit down to 6hp, drinks the potion
+        // back to full hp and finishes the enemy
+        assert!(game.battle(&mut enemy, false, false).unwrap());
+        assert!(!game.inventory().contains_key(&Key::Potion));
+        assert_eq!(game.player.max_hp(), game.player.current_hp);
+        assert!(!game
+            .achievements
+            .is_unlocked(crate::achievement::Achievement::Flawless));
+    }
+
+    #[test]
+    fn prestige() {
+        let mut game = Game::new();
+        assert!(game.prestige().is_err());
+        assert_eq!(0, game.player.prestige);
+
+        // unlock and complete the final quest
+        game.player.level = 50;
+        quest::level_up(&mut game, 1);
+        let mut gorthaur = character::Character::player();
+        gorthaur.class.name = String::from("gorthaur");
+        quest::battle_won(&mut game, &gorthaur, 0);
+        assert!(game.quests.gorthaur_beaten());
+
+        game.ring_pool.clear();
+        game.dungeons
+            .entry(String::from("/dev/game"))
+            .or_default()
+            .cleared = true;
+        let base_hp = game.player.max_hp();
+        assert!(game.prestige().is_ok());
+
+        assert_eq!(1, game.player.prestige);
+        assert_eq!(1, game.player.level);
+        assert_eq!(game.player.max_hp(), game.player.current_hp);
+        assert!(game.player.max_hp() > base_hp);
+        assert_eq!(Game::initial_ring_pool(), game.ring_pool);
+
+        // quests and dungeons are started over
+        assert!(!game.quests.gorthaur_beaten());
+        assert!(game.dungeons.is_empty());
+        assert!(game.prestige().is_err());
+
+        // the tier survives dying
+        game.reset();
+        assert_eq!(1, game.player.prestige);
+    }
+
+    #[test]
+    fn camp() {
+        let mut game = Game::new();
+        game.location = crate::location::tests::location_from("~/..");
+        game.player.current_hp = 1;
+        game.player.fatigue = 80;
+
+        game.camp().unwrap();
+        assert!(game.player.current_hp > 1);
+        assert_eq!(30, game.player.fatigue);
+    }
+
     #[test]
     fn battle_lost() {
         let mut game = Game::new();
@@ -534,5 +1089,122 @@ mod tests {
         let mut enemy = character::Character::new(enemy_class.clone(), 10);
         let result = game.battle(&mut enemy, false, false);
         assert!(result.is_err());
+
+        assert_eq!(Some(&1), game.stats.battles_lost.get(&enemy.name()));
+        assert_eq!(Some(&1), game.stats.deaths.get(&enemy.name()));
+
+        // stats survive the hero's death
+        game.reset();
+        assert_eq!(Some(&1), game.stats.deaths.get(&enemy.name()));
+    }
+
+    #[test]
+    fn battle_items() {
+        let mut game = Game::new();
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 10);
+
+        // battle items can't be used outside battles
+        game.add_item(Box::new(item::Bomb::new(1)));
+        game.add_item(Box::new(item::SmokeBomb::new()));
+        assert!(game.use_item(Key::Bomb).is_err());
+        assert!(game.use_item(Key::SmokeBomb).is_err());
+        assert_eq!(1, *game.inventory().get(&Key::Bomb).unwrap());
+
+        // the smoke bomb guarantees running away
+        assert!(!game.battle(&mut enemy, true, false).unwrap());
+        assert!(!game.inventory().contains_key(&Key::SmokeBomb));
+        assert_eq!(Some(&1), game.stats.items_used.get(&Key::SmokeBomb));
+
+        // the bomb is thrown in the first turn of a bomb battle
+        let mut enemy = character::Character::new(enemy_class.clone(), 2);
+        assert!(game.bomb_battle(&mut enemy).is_ok());
+        assert_eq!(Some(&1), game.stats.items_used.get(&Key::Bomb));
+        assert!(!game.inventory().contains_key(&Key::Bomb));
+
+        // warp scrolls need a tombstone to go to
+        let mut enemy = character::Character::new(enemy_class.clone(), 10);
+        assert!(game.battle(&mut enemy, false, false).is_err());
+        game.reset();
+        game.add_item(Box::new(item::Warp::new()));
+        assert_eq!(Some(game.location.clone()), game.last_tombstone());
+        // the hero could die on arrival while suffering a status effect
+        game.player.status_effect = Some(character::StatusEffect::Poison);
+        assert!(game.use_item(Key::Warp).is_err());
+        game.player.status_effect = None;
+        assert!(game.use_item(Key::Warp).is_ok());
+        game.inspect();
+        game.add_item(Box::new(item::Warp::new()));
+        assert!(game.use_item(Key::Warp).is_err());
+    }
+
+    #[test]
+    fn drop_items() {
+        let mut game = Game::new();
+        assert!(game.drop_item(&Key::Potion, 1).is_err());
+
+        game.add_item(Box::new(item::Potion::new(1)));
+        game.add_item(Box::new(item::Potion::new(1)));
+        game.add_item(Box::new(item::Potion::new(1)));
+        assert!(game.drop_item(&Key::Potion, 0).is_err());
+        assert!(game.chests.is_empty());
+        game.drop_item(&Key::Potion, 2).unwrap();
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+
+        // dropping more than available leaves the rest
+        game.drop_item(&Key::Potion, 5).unwrap();
+        assert!(!game.inventory().contains_key(&Key::Potion));
+
+        // picked up again when inspecting the location
+        game.inspect();
+        assert_eq!(3, *game.inventory().get(&Key::Potion).unwrap());
+        assert!(game.chests.is_empty());
+    }
+
+    #[test]
+    fn locked_chest() {
+        let mut game = Game::new();
+        let mut chest = Chest::default();
+        chest.add(Box::new(item::Potion::new(1)));
+        game.locked_chests.insert(game.location.to_string(), chest);
+
+        // stays at the location until opened
+        game.inspect();
+        assert!(game.inventory().is_empty());
+        assert_eq!(1, game.locked_chests.len());
+
+        // keys are dropped by rare enemies
+        let enemy =
+            character::Character::new(class::Class::random(class::Category::Rare).clone(), 1);
+        game.battle_won(&enemy, 0);
+        assert_eq!(1, *game.inventory().get(&Key::Chest).unwrap());
+
+        game.inspect();
+        assert!(!game.inventory().contains_key(&Key::Chest));
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+        assert!(game.locked_chests.is_empty());
+
+        // left at the location when there's no room for them
+        while game.has_room(1) {
+            game.add_item(Box::new(item::Potion::new(1)));
+        }
+        game.battle_won(&enemy, 0);
+        assert!(!game.inventory().contains_key(&Key::Chest));
+        game.inventory.clear();
+        game.inspect();
+        assert_eq!(1, *game.inventory().get(&Key::Chest).unwrap());
+        game.inventory.clear();
+
+        // thieves can pick the lock instead
+        let mut chest = Chest::default();
+        chest.add(Box::new(item::Potion::new(1)));
+        game.locked_chests.insert(game.location.to_string(), chest);
+        game.open_locked_chest(true);
+        assert_eq!(1, game.locked_chests.len());
+
+        assert!(game.player.change_class("thief").is_ok());
+        game.open_locked_chest(true);
+        assert!(game.locked_chests.is_empty());
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
     }
 }
@@ -1,23 +1,37 @@
-use super::equipment::Equipment;
+use super::equipment::{Equipment, Rarity, MAX_DURABILITY};
 use super::key::Key;
 use super::ring;
 use super::stone;
-use super::{Escape, Ether, Item, Potion, Remedy};
+use super::{
+    Antidote, Bomb, BurnHeal, Elixir, Escape, Ether, HolyWater, Identify, Item, Potion, Remedy,
+    SmokeBomb, Warp,
+};
 use crate::game;
+use crate::log;
 use crate::randomizer::random;
 use crate::randomizer::Randomizer;
+use crate::scan;
 use rand::prelude::{IteratorRandom, SliceRandom};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Locked chests have this many times the gold of a regular one.
+const LOCKED_GOLD_FACTOR: i32 = 3;
+
 /// A chest is a bag of items that can be picked up by the hero.
 /// It can randomly appear at a location upon inspection, or dropped
-/// by the hero when they die.
+/// by the hero when they die. Some chests are found locked and stay at
+/// their location until opened.
 #[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Chest {
     items: Vec<Box<dyn Item>>,
     sword: Option<Equipment>,
     shield: Option<Equipment>,
+    helmet: Option<Equipment>,
+    armor: Option<Equipment>,
+    boots: Option<Equipment>,
+    amulet: Option<Equipment>,
     gold: i32,
 }
 
@@ -41,17 +55,23 @@ impl Chest {
         // To give the impression of "dynamic" chest contents, each content type
         // is randomized separately, and what's found is combined into a single
         // chest at the end
-        let mut gold_chest = random().gold_chest(distance);
+        let mut gold_chest = game.player.has_ring_set(ring::RingSet::TreasureHunter)
+            || random().gold_chest(distance);
         let mut equipment_chest = random().equipment_chest(distance);
         let mut ring_chest = random().ring_chest(distance);
         let mut item_chest_attempts = 3;
 
-        // If the chest ring is equipped, double the likelyhood of finding a chest
+        // If the chest ring is equipped, double the likelyhood of finding a chest.
+        // Crowded directories or ones with large files give extra chances too
+        let mut extra_rolls = scan::scan(game).richness();
         if game.player.double_chests() {
+            extra_rolls += 1;
+        }
+        for _ in 0..extra_rolls {
             gold_chest = gold_chest || random().gold_chest(distance);
             equipment_chest = equipment_chest || random().equipment_chest(distance);
             ring_chest = ring_chest || random().ring_chest(distance);
-            item_chest_attempts *= 2;
+            item_chest_attempts += 3;
         }
 
         let mut chest = Self::default();
@@ -60,9 +80,9 @@ impl Chest {
             chest.gold = game.player.gold_gained(game.player.level + distance.len());
         }
         if equipment_chest {
-            let (sword, shield) = random_equipment(distance.len());
-            chest.sword = sword;
-            chest.shield = shield;
+            let equipment = random_equipment(distance.len(), Rarity::Common);
+            let key = equipment.key();
+            *chest.slot(&key) = Some(equipment);
         }
 
         if ring_chest {
@@ -96,6 +116,33 @@ impl Chest {
         }
     }
 
+    /// Randomly generate a locked chest at the current location. These are
+    /// more frequent far from home and have better loot than regular chests.
+    pub fn generate_locked(game: &mut game::Game) -> Option<Self> {
+        if game.player.enemies_evaded() {
+            return None;
+        }
+
+        let distance = &game.location.distance_from_home();
+        if game.player.level > distance.len() + 10 || !random().locked_chest(distance) {
+            return None;
+        }
+
+        let level = game.player.rounded_level();
+        let mut chest = Self {
+            gold: game.player.gold_gained(game.player.level + distance.len()) * LOCKED_GOLD_FACTOR,
+            items: vec![random_item(level), random_item(level)],
+            ..Self::default()
+        };
+        let equipment = random_equipment(distance.len() + 5, Rarity::Rare);
+        let key = equipment.key();
+        *chest.slot(&key) = Some(equipment);
+        if let Some(ring) = random_ring(game) {
+            chest.items.push(Box::new(ring));
+        }
+        Some(chest)
+    }
+
     pub fn battle_loot(game: &mut game::Game) -> Option<Self> {
         // reuse item % from chests, but don't add extra gold
         // kind of hacky but does for now
@@ -109,8 +156,6 @@ impl Chest {
     pub fn drop(game: &mut game::Game) -> Self {
         let items: HashMap<Key, Vec<Box<dyn Item>>> = game.inventory.drain().collect();
         let mut items: Vec<Box<dyn Item>> = items.into_values().flatten().collect();
-        let sword = game.player.sword.take();
-        let shield = game.player.shield.take();
 
         // equipped rings should be dropped as items
         if let Some(ring) = game.player.left_ring.take() {
@@ -119,79 +164,192 @@ impl Chest {
         if let Some(ring) = game.player.right_ring.take() {
             items.push(Box::new(ring));
         }
-        let gold = game.gold;
-
+        // depending on the death penalty, part of the gold may be lost
+        // or taken from the bank
+        let gold = game.bank.death_penalty(game.gold);
         game.gold = 0;
 
-        Self {
+        let mut chest = Self {
             items,
-            sword,
-            shield,
             gold,
+            ..Self::default()
+        };
+        for (slot, equipped) in chest.slots().into_iter().zip(game.player.equipment_slots()) {
+            *slot = equipped.take();
         }
+        chest
     }
 
     /// Add the items of this chest to the current game/hero
-    /// Return a picked up (item counts, gold) tuple.
-    pub fn pick_up(&mut self, game: &mut game::Game) -> (HashMap<Key, i32>, i32) {
+    /// Items that don't fit in the inventory are kept in the chest.
+    /// Return a picked up (item counts, gold) tuple, with the items by name.
+    pub fn pick_up(&mut self, game: &mut game::Game) -> (HashMap<String, i32>, i32) {
         let mut item_counts = HashMap::new();
 
-        // the equipment is picked up only if it's better than the current one
-        if maybe_upgrade(&mut game.player.sword, &mut self.sword) {
-            item_counts.insert(Key::Sword, 1);
-        }
-        if maybe_upgrade(&mut game.player.shield, &mut self.shield) {
-            item_counts.insert(Key::Shield, 1);
+        // the equipment is worn if it's better than the current one,
+        // otherwise it's kept in the inventory if there's room for it
+        for slot in self.slots() {
+            if let Some(equipment) = slot.take() {
+                // unknown pieces are never worn automatically, and cursed
+                // ones can't be replaced
+                // unknown pieces are never worn automatically, nor the ones
+                // the hero doesn't benefit from
+                let wearable = equipment.is_identified()
+                    && game.player.can_use(&equipment.key())
+                    && !game.player.is_slot_cursed(&equipment.key());
+                let current = game.player.slot(&equipment.key()).unwrap();
+                if wearable && equipment.is_upgrade_from(current) {
+                    *item_counts
+                        .entry(log::format_item_name(game, &equipment))
+                        .or_insert(0) += 1;
+                    // the replaced piece is kept too, or left in the chest
+                    if let Some(previous) = game.player.equip(equipment) {
+                        if game.has_room(previous.weight()) {
+                            game.add_item(Box::new(previous));
+                        } else {
+                            slot.replace(previous);
+                        }
+                    }
+                } else if game.has_room(equipment.weight()) {
+                    *item_counts
+                        .entry(log::format_item_name(game, &equipment))
+                        .or_insert(0) += 1;
+                    game.add_item(Box::new(equipment));
+                } else {
+                    slot.replace(equipment);
+                }
+            }
         }
 
-        // items and gold are always picked up
+        let mut left = Vec::new();
         for item in self.items.drain(..) {
-            *item_counts.entry(item.key()).or_insert(0) += 1;
-            game.add_item(item);
+            if game.has_room(item.weight()) {
+                *item_counts
+                    .entry(log::format_item_name(game, item.as_ref()))
+                    .or_insert(0) += 1;
+                game.add_item(item);
+            } else {
+                left.push(item);
+            }
         }
+        self.items = left;
+
+        // gold is always picked up
+        let gold = self.gold;
+        game.gold += gold;
+        self.gold = 0;
+        (item_counts, gold)
+    }
+
+    /// Add an item to the chest, e.g. when dropped by the hero.
+    pub fn add(&mut self, item: Box<dyn Item>) {
+        self.items.push(item);
+    }
 
-        game.gold += self.gold;
-        (item_counts, self.gold)
+    /// Return true if there's nothing left in the chest.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+            && self.gold == 0
+            && [
+                &self.sword,
+                &self.shield,
+                &self.helmet,
+                &self.armor,
+                &self.boots,
+                &self.amulet,
+            ]
+            .iter()
+            .all(|slot| slot.is_none())
     }
 
     /// Add the elements of `other` to this chest
     pub fn extend(&mut self, mut other: Self) {
         // keep the best of each equipment
-        maybe_upgrade(&mut self.sword, &mut other.sword);
-        maybe_upgrade(&mut self.shield, &mut other.shield);
+        for (current, other) in self.slots().into_iter().zip(other.slots()) {
+            maybe_upgrade(current, other);
+        }
         self.items.append(&mut other.items);
         self.gold += other.gold;
     }
+
+    /// The equipment slots of the chest, in the same order as the hero's.
+    fn slots(&mut self) -> [&mut Option<Equipment>; 6] {
+        [
+            &mut self.sword,
+            &mut self.shield,
+            &mut self.helmet,
+            &mut self.armor,
+            &mut self.boots,
+            &mut self.amulet,
+        ]
+    }
+
+    fn slot(&mut self, key: &Key) -> &mut Option<Equipment> {
+        match key {
+            key if key.is_weapon() => &mut self.sword,
+            Key::Shield => &mut self.shield,
+            Key::Helmet => &mut self.helmet,
+            Key::Armor => &mut self.armor,
+            Key::Boots => &mut self.boots,
+            _ => &mut self.amulet,
+        }
+    }
 }
 
 /// Upgrades current with the other equipment if it has a better level (or current is None).
 /// Return whether there was an upgrade.
 fn maybe_upgrade(current: &mut Option<Equipment>, other: &mut Option<Equipment>) -> bool {
-    if let Some(shield) = other.take() {
-        if shield.is_upgrade_from(current) {
-            current.replace(shield);
+    if let Some(equipment) = other.take() {
+        if equipment.is_upgrade_from(current) {
+            current.replace(equipment);
             return true;
         }
     }
     false
 }
 
-fn random_equipment(distance: i32) -> (Option<Equipment>, Option<Equipment>) {
+/// Return a random piece of equipment for the given distance, with at least
+/// the given rarity.
+fn random_equipment(distance: i32, min_rarity: Rarity) -> Equipment {
     let mut rng = rand::thread_rng();
 
     let level = std::cmp::max(1, (distance / 5) * 5);
 
-    [
-        (100, (Some(Equipment::sword(level)), None)),
-        (80, (None, Some(Equipment::shield(level)))),
-        (30, (Some(Equipment::sword(level + 5)), None)),
-        (20, (None, Some(Equipment::shield(level + 5)))),
-        (1, (Some(Equipment::sword(100)), None)),
+    let equipment = [
+        (100, Equipment::sword(level)),
+        (25, Equipment::dagger(level)),
+        (25, Equipment::staff(level)),
+        (25, Equipment::axe(level)),
+        (25, Equipment::bow(level)),
+        (80, Equipment::shield(level)),
+        (40, Equipment::helmet(level)),
+        (40, Equipment::armor(level)),
+        (40, Equipment::boots(level)),
+        (20, Equipment::amulet(level)),
+        (30, Equipment::sword(level + 5)),
+        (20, Equipment::shield(level + 5)),
+        (1, Equipment::sword(100)),
     ]
     .choose_weighted_mut(&mut rng, |c| c.0)
     .unwrap()
     .to_owned()
-    .1
+    .1;
+
+    // weapons and shields found in chests may be already worn out
+    let equipment = if equipment.wears_out() {
+        let durability = MAX_DURABILITY / 2 + random().range(MAX_DURABILITY / 2);
+        equipment.with_durability(durability)
+    } else {
+        equipment
+    };
+    let equipment = equipment.with_rarity(std::cmp::max(min_rarity, random().equipment_rarity()));
+
+    // some pieces need to be identified, and may be cursed
+    if random().unidentified() {
+        equipment.unidentified(random().curse())
+    } else {
+        equipment
+    }
 }
 
 /// Return a weigthed random item.
@@ -201,6 +359,14 @@ fn random_item(level: i32) -> Box<dyn Item> {
         (10, Box::new(Remedy::new())),
         (10, Box::new(Escape::new())),
         (50, Box::new(Ether::new(level))),
+        (20, Box::new(Bomb::new(level))),
+        (10, Box::new(SmokeBomb::new())),
+        (15, Box::new(Antidote::new())),
+        (15, Box::new(BurnHeal::new())),
+        (5, Box::new(Warp::new())),
+        (10, Box::new(Identify::new())),
+        (5, Box::new(HolyWater::new())),
+        (3, Box::new(Elixir::new())),
         (5, Box::new(stone::Health)),
         (5, Box::new(stone::Magic)),
         (5, Box::new(stone::Power)),
@@ -222,11 +388,20 @@ fn random_item(level: i32) -> Box<dyn Item> {
 
 fn random_ring(game: &mut game::Game) -> Option<ring::Ring> {
     let mut rng = rand::thread_rng();
-    if let Some(ring) = game.ring_pool.iter().choose(&mut rng).cloned() {
-        game.ring_pool.take(&ring)
-    } else {
-        None
+    let ring = game.ring_pool.iter().choose(&mut rng).cloned()?;
+    let ring = game.ring_pool.take(&ring)?;
+
+    // like equipment, some rings need to be identified, and may be cursed
+    let player = &mut game.player;
+    player.ring_curses.remove(&ring);
+    player.unidentified_rings.remove(&ring);
+    if random().unidentified() {
+        player.unidentified_rings.insert(ring.clone());
+        if let Some(curse) = random().curse() {
+            player.ring_curses.insert(ring.clone(), curse);
+        }
     }
+    Some(ring)
 }
 
 #[cfg(test)]
@@ -301,15 +476,58 @@ mod tests {
 
         assert_eq!(150, game.gold);
 
-        // the sword was upgrade, picked it up
+        // the sword was a downgrade, kept the current one and the other
+        // one in the inventory
         assert_eq!(5, game.player.sword.as_ref().unwrap().level());
+        assert_eq!(1, *game.inventory().get(&Key::Sword).unwrap());
 
-        // the shield was downgrade, kept the current one
+        // the shield was an upgrade, picked it up and kept the replaced
+        // one in the inventory
         assert_eq!(10, game.player.shield.as_ref().unwrap().level());
+        let shields = game.inventory.get(&Key::Shield).unwrap();
+        assert_eq!(1, shields.len());
+        assert_eq!(5, shields[0].as_equipment().unwrap().level());
 
         assert_eq!(3, *game.inventory().get(&Key::Potion).unwrap());
     }
 
+    #[test]
+    fn test_pickup_overflow() {
+        let mut game = game::Game::new();
+        let capacity = game.player.carry_capacity();
+        for _ in 0..capacity - 1 {
+            game.add_item(Box::new(Potion::new(1)));
+        }
+
+        let mut chest = Chest {
+            items: vec![Box::new(Potion::new(1)), Box::new(Escape::new())],
+            shield: Some(Equipment::shield(1)),
+            gold: 10,
+            ..Default::default()
+        };
+        game.player.shield = Some(Equipment::shield(5));
+
+        let (items, gold) = chest.pick_up(&mut game);
+        assert_eq!(10, gold);
+        assert_eq!(capacity, game.inventory_weight());
+        assert_eq!(1, *items.get("potion").unwrap());
+        assert!(!items.contains_key("shield"));
+
+        // the overflow is kept in the chest
+        assert!(!chest.is_empty());
+        assert_eq!(0, chest.gold);
+        assert_eq!(1, chest.items.len());
+        assert!(chest.shield.is_some());
+
+        // left behind at the location and picked up when there's room
+        game.chests.insert(game.location.to_string(), chest);
+        game.take_item(&Key::Potion);
+        game.take_item(&Key::Potion);
+        game.inspect();
+        assert!(game.inventory().contains_key(&Key::Escape));
+        assert!(game.chests.contains_key(&game.location.to_string()));
+    }
+
     #[test]
     fn test_merge() {
         let items: Vec<Box<dyn Item>> = vec![Box::new(Potion::new(1)), Box::new(Potion::new(1))];
@@ -318,6 +536,7 @@ mod tests {
             sword: Some(Equipment::sword(1)),
             shield: Some(Equipment::shield(10)),
             gold: 100,
+            ..Chest::default()
         };
 
         let items: Vec<Box<dyn Item>> = vec![Box::new(Potion::new(1)), Box::new(Escape::new())];
@@ -325,13 +544,16 @@ mod tests {
             items,
             sword: Some(Equipment::sword(10)),
             shield: Some(Equipment::shield(1)),
+            boots: Some(Equipment::boots(5)),
             gold: 100,
+            ..Chest::default()
         };
 
         chest1.extend(chest2);
         assert_eq!(200, chest1.gold);
         assert_eq!(10, chest1.sword.as_ref().unwrap().level());
         assert_eq!(10, chest1.shield.as_ref().unwrap().level());
+        assert_eq!(5, chest1.boots.as_ref().unwrap().level());
         let item_keys = chest1.items.iter().map(|i| i.key()).collect::<Vec<_>>();
         assert_eq!(
             vec![Key::Potion, Key::Potion, Key::Potion, Key::Escape],
@@ -0,0 +1,365 @@
+use super::equipment::{Affix, Equipment};
+use super::key::Key;
+use crate::game::Game;
+use crate::log;
+use anyhow::{bail, Result};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A way of turning items of the inventory into a different one.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Recipe {
+    /// Consume the ingredients to produce the result item.
+    Combine { ingredients: Vec<Key>, result: Key },
+
+    /// Consume two pieces of the same kind and level to produce a single one
+    /// with a higher level.
+    Merge { levels: i32 },
+
+    /// Consume a ring to add an affix to the equipped weapon.
+    Infuse { ring: Key, affix: Affix },
+
+    /// Consume the ingredients to raise the level of an owned stat ring.
+    Upgrade { ingredients: Vec<Key> },
+}
+
+static RECIPES: OnceCell<Vec<Recipe>> = OnceCell::new();
+
+/// Customize the recipe definitions based on an input yaml byte array.
+pub fn load(bytes: &[u8]) {
+    RECIPES.set(serde_yaml::from_slice(bytes).unwrap()).unwrap();
+}
+
+fn recipes() -> &'static Vec<Recipe> {
+    RECIPES.get_or_init(|| serde_yaml::from_slice(include_bytes!("recipes.yaml")).unwrap())
+}
+
+/// Print the known recipes, highlighting the ones that can be crafted
+/// with the current inventory.
+pub fn list(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Crafting is only allowed at home.");
+    }
+
+    let recipes = recipes()
+        .iter()
+        .map(|recipe| (recipe.is_available(game), recipe.to_string()))
+        .collect();
+    log::recipe_list(recipes);
+    Ok(())
+}
+
+/// Craft the given item using the first matching recipe that can be applied.
+pub fn craft(game: &mut Game, key: &Key) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Crafting is only allowed at home.");
+    }
+
+    let mut error = None;
+    for recipe in recipes().iter().filter(|recipe| recipe.produces(key)) {
+        match recipe.apply(game, key) {
+            Ok(crafted) => {
+                log::craft(&crafted);
+                return Ok(());
+            }
+            Err(err) => error = error.or(Some(err)),
+        }
+    }
+
+    if let Some(error) = error {
+        Err(error)
+    } else {
+        bail!("no recipe for {}.", key)
+    }
+}
+
+impl Recipe {
+    /// Return true if the recipe can be used to craft the given item.
+    /// For merges, this is the kind of equipment; for infusions, the ring.
+    fn produces(&self, key: &Key) -> bool {
+        match self {
+            Recipe::Combine { result, .. } => result == key,
+            Recipe::Merge { .. } => key.is_equipment(),
+            Recipe::Infuse { ring, .. } => ring == key,
+            Recipe::Upgrade { .. } => {
+                matches!(key, Key::Ring(ring) if ring.is_upgradable())
+            }
+        }
+    }
+
+    fn is_available(&self, game: &Game) -> bool {
+        match self {
+            Recipe::Combine { ingredients, .. } => has_ingredients(game, ingredients),
+            Recipe::Merge { .. } => game
+                .inventory
+                .keys()
+                .any(|key| mergeable_level(game, key).is_some()),
+            Recipe::Infuse { ring, .. } => {
+                game.inventory.contains_key(ring) && game.player.sword.is_some()
+            }
+            Recipe::Upgrade { ingredients } => has_ingredients(game, ingredients),
+        }
+    }
+
+    /// Consume the inputs of the recipe to craft the given item.
+    /// Return the display name of the crafted item.
+    fn apply(&self, game: &mut Game, key: &Key) -> Result<String> {
+        match self {
+            Recipe::Combine {
+                ingredients,
+                result,
+            } => {
+                if !has_ingredients(game, ingredients) {
+                    bail!("missing ingredients for {}.", result);
+                }
+                let Some(item) = super::from_key(result, game.player.rounded_level()) else {
+                    bail!("{} can't be crafted.", result);
+                };
+
+                for ingredient in ingredients {
+                    game.take_item(ingredient);
+                }
+                let crafted = item.to_string();
+                game.add_or_leave(item);
+                Ok(crafted)
+            }
+            Recipe::Merge { levels } => {
+                let Some(level) = mergeable_level(game, key) else {
+                    bail!("two {} pieces of the same level are required.", key);
+                };
+
+                // prefer merging into the equipped piece, if it matches
+                let stored = take_equipment(game, key, level).unwrap();
+                let equipped = game.player.equipped(key).cloned();
+                if let Some(equipped) = equipped.filter(|piece| piece.level() == level) {
+                    let merged = equipped.merge(stored, *levels);
+                    let crafted = merged.to_string();
+                    game.player.equip(merged);
+                    Ok(crafted)
+                } else {
+                    let other = take_equipment(game, key, level).unwrap();
+                    let merged = stored.merge(other, *levels);
+                    let crafted = merged.to_string();
+                    game.add_or_leave(Box::new(merged));
+                    Ok(crafted)
+                }
+            }
+            Recipe::Infuse { ring, affix } => {
+                let Key::Ring(ring_type) = ring else {
+                    bail!("{} is not a ring.", ring);
+                };
+                if !game.inventory.contains_key(ring) {
+                    bail!("{} not found in the inventory.", ring);
+                }
+                let Some(weapon) = game.player.sword.as_mut() else {
+                    bail!("a weapon must be equipped to infuse {}.", ring);
+                };
+                if !weapon.add_affix(*affix) {
+                    bail!("{} can't take the {} affix.", weapon, affix);
+                }
+
+                let crafted = weapon.to_string();
+                // the ring is lost, so it can be found again
+                game.take_item(ring);
+                game.ring_pool.insert(ring_type.clone());
+                Ok(crafted)
+            }
+            Recipe::Upgrade { ingredients } => {
+                let Key::Ring(ring) = key else {
+                    bail!("{} is not a ring.", key);
+                };
+                if !game.player.wears(ring.clone()) && !game.inventory.contains_key(key) {
+                    bail!("{} not found.", key);
+                }
+                if !has_ingredients(game, ingredients) {
+                    bail!("missing ingredients to upgrade {}.", key);
+                }
+                if !game.player.upgrade_ring(ring) {
+                    bail!("{} can't be upgraded any further.", key);
+                }
+
+                for ingredient in ingredients {
+                    game.take_item(ingredient);
+                }
+                Ok(format!("{}[{}]", ring, game.player.ring_level(ring)))
+            }
+        }
+    }
+}
+
+fn has_ingredients(game: &Game, ingredients: &[Key]) -> bool {
+    let mut required = HashMap::new();
+    for ingredient in ingredients {
+        *required.entry(ingredient).or_insert(0) += 1;
+    }
+
+    let inventory = game.inventory();
+    required
+        .iter()
+        .all(|(key, count)| inventory.get(key).copied().unwrap_or(0) >= *count)
+}
+
+/// Return the levels of the pieces of the given kind kept in the inventory.
+fn stored_levels(game: &Game, key: &Key) -> Vec<i32> {
+    game.inventory.get(key).map_or(vec![], |items| {
+        items
+            .iter()
+            .filter_map(|item| item.as_equipment())
+            .map(|piece| piece.level())
+            .collect()
+    })
+}
+
+/// Return a level for which there are two pieces of the given kind, either
+/// equipped or in the inventory.
+fn mergeable_level(game: &Game, key: &Key) -> Option<i32> {
+    let mut levels = stored_levels(game, key);
+    if let Some(equipped) = game.player.equipped(key) {
+        levels.push(equipped.level());
+    }
+
+    levels
+        .iter()
+        .copied()
+        .find(|level| levels.iter().filter(|other| *other == level).count() >= 2)
+}
+
+/// Remove a piece of the given kind and level from the inventory.
+fn take_equipment(game: &mut Game, key: &Key, level: i32) -> Option<Equipment> {
+    let items = game.inventory.get_mut(key)?;
+    let index = items.iter().position(|item| {
+        item.as_equipment()
+            .is_some_and(|piece| piece.level() == level)
+    })?;
+    let piece = items.remove(index).as_equipment().cloned();
+    if items.is_empty() {
+        game.inventory.remove(key);
+    }
+    piece
+}
+
+impl fmt::Display for Recipe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recipe::Combine {
+                ingredients,
+                result,
+            } => {
+                let ingredients: Vec<String> = ingredients.iter().map(|i| i.to_string()).collect();
+                write!(f, "{} -> {}", ingredients.join(" + "), result)
+            }
+            Recipe::Merge { levels } => {
+                write!(f, "2 equal equipment pieces -> level +{}", levels)
+            }
+            Recipe::Infuse { ring, affix } => write!(f, "{} -> weapon ({})", ring, affix),
+            Recipe::Upgrade { ingredients } => {
+                let ingredients: Vec<String> = ingredients.iter().map(|i| i.to_string()).collect();
+                write!(
+                    f,
+                    "stat ring + {} -> ring level +1",
+                    ingredients.join(" + ")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::ring::Ring;
+    use crate::item::{stone, Potion, Remedy};
+
+    #[test]
+    fn combine() {
+        let mut game = Game::new();
+        assert!(craft(&mut game, &Key::Elixir).is_err());
+
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(Remedy::new()));
+        assert!(craft(&mut game, &Key::Elixir).is_ok());
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+        assert!(!game.inventory().contains_key(&Key::Remedy));
+        assert_eq!(1, *game.inventory().get(&Key::Elixir).unwrap());
+
+        // any of the stone recipes can be used
+        game.add_item(Box::new(stone::Speed));
+        game.add_item(Box::new(stone::Speed));
+        assert!(craft(&mut game, &Key::LevelStone).is_err());
+        game.add_item(Box::new(stone::Speed));
+        assert!(craft(&mut game, &Key::LevelStone).is_ok());
+        assert!(!game.inventory().contains_key(&Key::SpeedStone));
+        assert_eq!(1, *game.inventory().get(&Key::LevelStone).unwrap());
+
+        // crafted items survive serialization
+        let data = serde_json::to_vec(&game).unwrap();
+        let mut game: Game = serde_json::from_slice(&data).unwrap();
+        assert!(game.use_item(Key::Elixir).is_ok());
+    }
+
+    #[test]
+    fn merge() {
+        let mut game = Game::new();
+        game.player.shield = Some(Equipment::shield(5));
+        game.add_item(Box::new(Equipment::shield(1)));
+        assert!(craft(&mut game, &Key::Shield).is_err());
+
+        // two pieces in the inventory
+        game.add_item(Box::new(Equipment::shield(1)));
+        assert!(craft(&mut game, &Key::Shield).is_ok());
+        assert_eq!(stored_levels(&game, &Key::Shield), vec![6]);
+        assert_eq!(5, game.player.shield.as_ref().unwrap().level());
+
+        // equipped and stored piece
+        game.add_item(Box::new(Equipment::shield(5)));
+        assert!(craft(&mut game, &Key::Shield).is_ok());
+        assert_eq!(10, game.player.shield.as_ref().unwrap().level());
+        assert_eq!(stored_levels(&game, &Key::Shield), vec![6]);
+    }
+
+    #[test]
+    fn infuse() {
+        let mut game = Game::new();
+        game.ring_pool.remove(&Ring::Fire);
+        game.add_item(Box::new(Ring::Fire));
+        assert!(craft(&mut game, &Key::Ring(Ring::Fire)).is_err());
+
+        game.player.sword = Some(Equipment::sword(1));
+        assert!(craft(&mut game, &Key::Ring(Ring::Fire)).is_ok());
+        let sword = game.player.sword.as_ref().unwrap();
+        assert!(sword.has_affix(Affix::Fire));
+        assert!(!game.inventory().contains_key(&Key::Ring(Ring::Fire)));
+        assert!(game.ring_pool.contains(&Ring::Fire));
+
+        // can't infuse the same affix twice
+        game.add_item(Box::new(Ring::Fire));
+        assert!(craft(&mut game, &Key::Ring(Ring::Fire)).is_err());
+
+        // no recipe for other rings
+        game.add_item(Box::new(Ring::Void));
+        assert!(craft(&mut game, &Key::Ring(Ring::Void)).is_err());
+    }
+
+    #[test]
+    fn upgrade() {
+        let mut game = Game::new();
+        game.add_item(Box::new(stone::Level));
+        assert!(craft(&mut game, &Key::Ring(Ring::Attack)).is_err());
+
+        game.ring_pool.remove(&Ring::Attack);
+        game.add_item(Box::new(Ring::Attack));
+        assert!(craft(&mut game, &Key::Ring(Ring::Attack)).is_ok());
+        assert_eq!(2, game.player.ring_level(&Ring::Attack));
+        assert!(!game.inventory().contains_key(&Key::LevelStone));
+
+        // only stat rings can be upgraded
+        game.add_item(Box::new(stone::Level));
+        game.ring_pool.remove(&Ring::Void);
+        game.add_item(Box::new(Ring::Void));
+        assert!(craft(&mut game, &Key::Ring(Ring::Void)).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use super::Item;
+use crate::game::Game;
+use crate::log;
+use anyhow::{bail, Result};
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Gold paid at home to identify each unknown item.
+const IDENTIFY_COST: i32 = 250;
+
+/// Hidden downside of a cursed ring or equipment piece. It's revealed when
+/// the item is worn, and it can't be removed until purified.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub enum Curse {
+    /// Lose hp on every turn, like the ruling ring.
+    Drain,
+    /// Enemies appear more often, even with the evade ring.
+    Lure,
+}
+
+impl Curse {
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        Curse::iter().choose(&mut rng).unwrap()
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Curse::Drain => "drains hp on every turn",
+            Curse::Lure => "lures enemies",
+        }
+    }
+}
+
+impl fmt::Display for Curse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Curse::Drain => "drain",
+            Curse::Lure => "lure",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Pay gold to identify all the unknown items in the inventory.
+pub fn identify(game: &mut Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Identifying items is only available at home.");
+    }
+
+    let count = game
+        .inventory
+        .values()
+        .flatten()
+        .filter(|item| !game.is_identified(item.as_ref()))
+        .count() as i32;
+    if count == 0 {
+        bail!("Nothing to identify.");
+    }
+
+    let cost = count * IDENTIFY_COST;
+    if game.gold < cost {
+        bail!("Not enough gold.");
+    }
+    game.gold -= cost;
+    let identified = game.identify_all();
+    log::identified(&identified, cost);
+    Ok(())
+}
+
+/// Return the name shown for an item while it's not identified,
+/// e.g. `???-rng`.
+pub fn unknown_name(item: &dyn Item) -> String {
+    let key = item.key().to_string();
+    let kind = key.rsplit('-').next().unwrap_or(&key);
+    format!("???-{}", kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::equipment::Equipment;
+    use crate::item::key::Key;
+    use crate::item::ring::Ring;
+    use crate::item::HolyWater;
+
+    #[test]
+    fn identify_items() {
+        let mut game = Game::new();
+        assert!(identify(&mut game).is_err());
+
+        game.player.unidentified_rings.insert(Ring::Speed);
+        game.player.ring_curses.insert(Ring::Speed, Curse::Drain);
+        game.add_item(Box::new(Ring::Speed));
+        game.add_item(Box::new(Equipment::shield(5).unidentified(None)));
+        let inventory = log::format_inventory(&game);
+        assert!(inventory.contains("???-rng"));
+        assert!(inventory.contains("???-shield"));
+        assert!(!inventory.contains("spd-rng"));
+
+        assert!(identify(&mut game).is_err());
+        game.gold = 2 * IDENTIFY_COST;
+        assert!(identify(&mut game).is_ok());
+        assert_eq!(0, game.gold);
+        let inventory = log::format_inventory(&game);
+        assert!(inventory.contains("spd-rng"));
+        assert!(inventory.contains("shield"));
+        assert!(!inventory.contains("???"));
+    }
+
+    #[test]
+    fn cursed_ring() {
+        let mut game = Game::new();
+        game.player.unidentified_rings.insert(Ring::Speed);
+        game.player.ring_curses.insert(Ring::Speed, Curse::Lure);
+        game.add_item(Box::new(Ring::Speed));
+
+        // the curse is revealed when worn
+        game.use_item(Key::Ring(Ring::Speed)).unwrap();
+        assert!(game.player.unidentified_rings.is_empty());
+        assert!(game.player.has_curse(Curse::Lure));
+        assert!(game.use_item(Key::Ring(Ring::Speed)).is_err());
+        assert!(game.player.wears(Ring::Speed));
+
+        // other rings replace the non cursed one
+        game.add_item(Box::new(Ring::Attack));
+        game.use_item(Key::Ring(Ring::Attack)).unwrap();
+        game.add_item(Box::new(Ring::Deffense));
+        game.use_item(Key::Ring(Ring::Deffense)).unwrap();
+        assert!(game.player.wears(Ring::Speed));
+        assert!(game.player.wears(Ring::Deffense));
+
+        game.add_item(Box::new(HolyWater::new()));
+        game.use_item(Key::HolyWater).unwrap();
+        assert!(game.player.curses().is_empty());
+        assert!(game.use_item(Key::Ring(Ring::Speed)).is_ok());
+        assert!(!game.player.wears(Ring::Speed));
+    }
+
+    #[test]
+    fn cursed_equipment() {
+        let mut game = Game::new();
+        game.add_item(Box::new(
+            Equipment::sword(1).unidentified(Some(Curse::Drain)),
+        ));
+        game.use_item(Key::Sword).unwrap();
+        assert!(game.player.sword.as_ref().unwrap().is_identified());
+        assert!(game.player.has_curse(Curse::Drain));
+
+        // no weapon can replace it
+        game.add_item(Box::new(Equipment::dagger(10)));
+        assert!(game.use_item(Key::Dagger).is_err());
+        assert!(game.inventory().contains_key(&Key::Dagger));
+
+        game.add_item(Box::new(HolyWater::new()));
+        game.use_item(Key::HolyWater).unwrap();
+        assert!(game.use_item(Key::Dagger).is_ok());
+        assert!(game.inventory().contains_key(&Key::Sword));
+    }
+}
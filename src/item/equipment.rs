@@ -1,57 +1,479 @@
 use core::fmt;
 
+use super::curse::Curse;
 use super::key::Key;
+use super::shop::Shoppable;
+use super::Item;
 use crate::character::class::Class;
+use crate::game::Game;
+use crate::log;
+use crate::randomizer::{random, Randomizer};
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
-/// Equipment piece with a strength contribution based on
-/// a level. Used to generically represent swords and shields.
+/// How many hits an equipment piece can take before breaking.
+pub const MAX_DURABILITY: i32 = 200;
+
+/// Maximum status resistance percentage granted by an amulet.
+const MAX_RESISTANCE: i32 = 75;
+
+/// Equipment piece with a stat contribution based on a level.
+/// Used to generically represent weapons, shields and armor pieces.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Equipment(Key, i32);
+pub struct Equipment {
+    key: Key,
+    level: i32,
+
+    /// Decreases with each hit dealt (weapons) or received (shields).
+    #[serde(default = "max_durability")]
+    durability: i32,
+
+    #[serde(default)]
+    rarity: Rarity,
+
+    #[serde(default)]
+    affixes: Vec<Affix>,
+
+    /// Hidden downside that prevents removing the piece once worn.
+    #[serde(default)]
+    curse: Option<Curse>,
+
+    /// Unidentified pieces hide their rarity, affixes and curse.
+    #[serde(default)]
+    unidentified: bool,
+}
+
+fn max_durability() -> i32 {
+    MAX_DURABILITY
+}
+
+/// Determines how many affixes a piece of equipment has. Unique pieces
+/// also get a bonus on their base stat.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Magic,
+    Rare,
+    Unique,
+}
+
+impl Rarity {
+    fn affix_count(&self) -> usize {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Magic => 1,
+            Rarity::Rare => 2,
+            Rarity::Unique => 3,
+        }
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rarity::Common => "common",
+            Rarity::Magic => "magic",
+            Rarity::Rare => "rare",
+            Rarity::Unique => "unique",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Extra effects randomly attached to non-common equipment.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub enum Affix {
+    /// Chance to burn the enemy on each attack.
+    Fire,
+    /// Recover a portion of the inflicted damage as hp.
+    Vampiric,
+    /// Increase speed.
+    Swift,
+    /// Increase gold gained in battle.
+    Lucky,
+}
+
+impl fmt::Display for Affix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Affix::Fire => "of fire",
+            Affix::Vampiric => "vampiric",
+            Affix::Swift => "swift",
+            Affix::Lucky => "lucky",
+        };
+        write!(f, "{}", name)
+    }
+}
 
 impl Equipment {
     pub fn sword(level: i32) -> Self {
-        Self(Key::Sword, level)
+        Self::new(Key::Sword, level)
+    }
+
+    pub fn dagger(level: i32) -> Self {
+        Self::new(Key::Dagger, level)
+    }
+
+    pub fn staff(level: i32) -> Self {
+        Self::new(Key::Staff, level)
+    }
+
+    pub fn axe(level: i32) -> Self {
+        Self::new(Key::Axe, level)
+    }
+
+    pub fn bow(level: i32) -> Self {
+        Self::new(Key::Bow, level)
+    }
+
+    /// Return the weapon kind that has affinity with the given class,
+    /// or a sword if there isn't one.
+    pub fn class_weapon(class: &Class, level: i32) -> Self {
+        match &class.affinity {
+            Some(key) if key.is_weapon() => Self::new(key.clone(), level),
+            _ => Self::sword(level),
+        }
     }
 
     pub fn shield(level: i32) -> Self {
-        Self(Key::Shield, level)
+        Self::new(Key::Shield, level)
+    }
+
+    pub fn helmet(level: i32) -> Self {
+        Self::new(Key::Helmet, level)
+    }
+
+    pub fn armor(level: i32) -> Self {
+        Self::new(Key::Armor, level)
+    }
+
+    pub fn boots(level: i32) -> Self {
+        Self::new(Key::Boots, level)
+    }
+
+    pub fn amulet(level: i32) -> Self {
+        Self::new(Key::Amulet, level)
+    }
+
+    pub fn new(key: Key, level: i32) -> Self {
+        Self {
+            key,
+            level,
+            durability: MAX_DURABILITY,
+            rarity: Rarity::Common,
+            affixes: Vec::new(),
+            curse: None,
+            unidentified: false,
+        }
+    }
+
+    /// Return a copy of this equipment with the given rarity and as many
+    /// randomly chosen affixes as it allows.
+    pub fn with_rarity(self, rarity: Rarity) -> Self {
+        let affixes = random().affixes(rarity.affix_count());
+        self.with_affixes(rarity, affixes)
+    }
+
+    /// Return a copy of this equipment with the given rarity and affixes.
+    pub fn with_affixes(mut self, rarity: Rarity, affixes: Vec<Affix>) -> Self {
+        self.rarity = rarity;
+        self.affixes = affixes;
+        self
+    }
+
+    /// Return a copy of this equipment that needs to be identified to know
+    /// its properties, cursed if a curse is given.
+    pub fn unidentified(mut self, curse: Option<Curse>) -> Self {
+        self.unidentified = true;
+        self.curse = curse;
+        self
+    }
+
+    pub fn is_identified(&self) -> bool {
+        !self.unidentified
+    }
+
+    /// Reveal the properties of the piece, including its curse.
+    pub fn identify(&mut self) {
+        self.unidentified = false;
+    }
+
+    pub fn curse(&self) -> Option<Curse> {
+        self.curse
+    }
+
+    /// Remove the curse from the piece. Return whether it was cursed.
+    pub fn purify(&mut self) -> bool {
+        self.curse.take().is_some()
+    }
+
+    pub fn rarity(&self) -> Rarity {
+        self.rarity
+    }
+
+    pub fn affixes(&self) -> &[Affix] {
+        &self.affixes
+    }
+
+    pub fn has_affix(&self, affix: Affix) -> bool {
+        self.affixes.contains(&affix)
+    }
+
+    /// Add the given affix if not already present and there's room for it,
+    /// raising the rarity accordingly. Return whether it was added.
+    pub fn add_affix(&mut self, affix: Affix) -> bool {
+        if self.has_affix(affix) || self.affixes.len() >= Rarity::Unique.affix_count() {
+            return false;
+        }
+
+        self.affixes.push(affix);
+        let rarity = match self.affixes.len() {
+            1 => Rarity::Magic,
+            2 => Rarity::Rare,
+            _ => Rarity::Unique,
+        };
+        self.rarity = std::cmp::max(self.rarity, rarity);
+        true
+    }
+
+    /// Combine with another piece of the same kind, keeping the best of both
+    /// with its level raised and its durability restored.
+    pub fn merge(self, other: Self, levels: i32) -> Self {
+        let mut best = if self.score() >= other.score() {
+            self
+        } else {
+            other
+        };
+        best.level += levels;
+        best.durability = MAX_DURABILITY;
+        best
+    }
+
+    /// Return a copy of this equipment with the given durability, e.g. to
+    /// include pre-damaged equipment in chests.
+    pub fn with_durability(mut self, durability: i32) -> Self {
+        self.durability = durability.clamp(1, MAX_DURABILITY);
+        self
     }
 
     pub fn level(&self) -> i32 {
-        self.1
+        self.level
     }
 
     pub fn key(&self) -> Key {
-        self.0.clone()
+        self.key.clone()
+    }
+
+    pub fn durability(&self) -> i32 {
+        self.durability
     }
 
-    /// How many strength points get added to the player when
-    /// the item is equipped.
+    /// How many stat points get added to the player when the item is
+    /// equipped. The stat depends on the kind of equipment: strength for
+    /// weapons and shields, max hp for armor, max mp for helmets, speed for
+    /// boots and status resistance percentage for amulets.
     pub fn strength(&self) -> i32 {
-        // get the base strength of the hero at this level
-        let player_strength = Class::player_first().strength.at(self.level());
+        let class = Class::player_first();
 
-        // calculate the added strength as a function of the player strength
-        (player_strength as f64 * 0.5).round() as i32
+        // calculate the added stat as a function of the base stat of the hero
+        // at this level. Each weapon kind scales differently
+        let (base, factor) = match self.key {
+            Key::Armor => (class.hp.at(self.level()), 0.25),
+            Key::Helmet => (class.strength.at(self.level()), 0.25),
+            Key::Boots => (class.speed.at(self.level()), 0.25),
+            Key::Amulet => return std::cmp::min(self.level(), MAX_RESISTANCE),
+            Key::Dagger => (class.strength.at(self.level()), 0.35),
+            Key::Staff => (class.strength.at(self.level()), 0.2),
+            Key::Axe => (class.strength.at(self.level()), 0.6),
+            Key::Bow => (class.strength.at(self.level()), 0.4),
+            _ => (class.strength.at(self.level()), 0.5),
+        };
+        self.scaled(base, factor)
+    }
+
+    /// Magic attack points added by staves.
+    pub fn magic_strength(&self) -> i32 {
+        if let Key::Staff = self.key {
+            // magic attacks are based on three times the strength
+            let base = Class::player_first().strength.at(self.level()) * 3;
+            self.scaled(base, 0.5)
+        } else {
+            0
+        }
+    }
+
+    /// Speed points added by daggers.
+    pub fn speed(&self) -> i32 {
+        if let Key::Dagger = self.key {
+            self.scaled(Class::player_first().speed.at(self.level()), 0.25)
+        } else {
+            0
+        }
+    }
+
+    /// Apply the given factor to a base stat, taking the condition of the
+    /// equipment into account.
+    fn scaled(&self, base: i32, factor: f64) -> i32 {
+        // worn equipment loses up to half of its strength
+        let condition = if self.wears_out() {
+            0.5 + self.durability as f64 / MAX_DURABILITY as f64
+        } else {
+            1.0
+        };
+        let condition = condition.min(1.0);
+
+        let rarity = if let Rarity::Unique = self.rarity {
+            1.2
+        } else {
+            1.0
+        };
+
+        (base as f64 * factor * condition * rarity).round() as i32
+    }
+
+    /// Return true for the pieces that lose durability in battle: weapons
+    /// when attacking and shields when hit.
+    pub fn wears_out(&self) -> bool {
+        self.key.is_weapon() || self.key == Key::Shield
+    }
+
+    /// Reduce the durability after a hit.
+    /// Return true if the equipment broke as a consequence.
+    pub fn wear(&mut self) -> bool {
+        self.durability = std::cmp::max(0, self.durability - 1);
+        self.durability == 0
     }
 
-    /// Return true if the other weapon either is None or has lower level than this one.
+    /// The gold it takes to restore the equipment to full durability,
+    /// proportional to half the price of a new piece of the same level.
+    pub fn repair_cost(&self) -> i32 {
+        if !self.wears_out() {
+            return 0;
+        }
+        self.level() * 250 * (MAX_DURABILITY - self.durability) / MAX_DURABILITY
+    }
+
+    pub fn repair(&mut self) {
+        self.durability = MAX_DURABILITY;
+    }
+
+    /// Return true if the other equipment either is None or has a lower
+    /// score than this one.
     pub fn is_upgrade_from(&self, maybe_other: &Option<Self>) -> bool {
         if let Some(equip) = maybe_other {
-            self.level() > equip.level()
+            self.score() > equip.score()
         } else {
             true
         }
     }
 
-    pub fn describe(&self) -> String {
-        let stat = if let Key::Sword = self.key() {
-            "physical attack"
+    /// A rough measure of how good this piece is, to compare equipment of
+    /// different levels and rarities: each affix is worth about five levels.
+    fn score(&self) -> i32 {
+        let unique_bonus = if let Rarity::Unique = self.rarity {
+            self.level / 5
         } else {
-            "defense"
+            0
         };
-        format!("increases {} by {}", stat, self.strength())
+        self.level + self.affixes.len() as i32 * 5 + unique_bonus
+    }
+
+    pub fn describe(&self) -> String {
+        if self.unidentified {
+            return String::from("unidentified");
+        }
+
+        let stat = match self.key() {
+            Key::Shield => "defense",
+            Key::Helmet => "max mp",
+            Key::Armor => "max hp",
+            Key::Boots => "speed",
+            Key::Amulet => return format!("reduces status ailment chance by {}%", self.strength()),
+            _ => "physical attack",
+        };
+        let mut description = format!("increases {} by {}", stat, self.strength());
+
+        match self.key() {
+            Key::Dagger => description.push_str(&format!(" and speed by {}", self.speed())),
+            Key::Staff => {
+                description.push_str(&format!(" and magic attack by {}", self.magic_strength()))
+            }
+            Key::Axe => description.push_str(" with higher critical chance"),
+            Key::Bow => description.push_str(", strikes first"),
+            _ => {}
+        }
+
+        let classes = Class::with_affinity(&self.key);
+        if !classes.is_empty() {
+            description.push_str(&format!(", {} affinity", classes.join("/")));
+        }
+
+        if self.wears_out() {
+            description.push_str(&format!(
+                " (durability {}/{})",
+                self.durability, MAX_DURABILITY
+            ));
+        }
+
+        if !self.affixes.is_empty() {
+            let affixes: Vec<String> = self.affixes.iter().map(|a| a.to_string()).collect();
+            description.push_str(&format!("; {}: {}", self.rarity, affixes.join(", ")));
+        }
+        if let Some(curse) = self.curse {
+            description.push_str(&format!("; cursed, {}", curse.describe()));
+        }
+        description
+    }
+}
+
+/// Equipment pieces that aren't worn are kept in the inventory.
+/// Using them equips them, storing the replaced piece instead.
+#[typetag::serde]
+impl Item for Equipment {
+    fn apply(&mut self, game: &mut Game) {
+        if let Some(curse) = self.curse {
+            log::cursed(&self.to_string(), curse);
+        }
+        if let Some(previous) = game.player.equip(self.clone()) {
+            game.add_or_leave(Box::new(previous));
+        }
+    }
+
+    fn check_use(&self, game: &Game) -> Result<()> {
+        if game.player.is_slot_cursed(&self.key) {
+            bail!("the equipped piece is cursed and can't be removed.");
+        }
+        Ok(())
+    }
+
+    fn key(&self) -> Key {
+        self.key.clone()
+    }
+
+    fn describe(&self) -> String {
+        Equipment::describe(self)
+    }
+
+    fn as_equipment(&self) -> Option<&Equipment> {
+        Some(self)
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn Shoppable> {
+        Some(self)
+    }
+
+    fn weight(&self) -> i32 {
+        match self.key {
+            Key::Armor => 5,
+            Key::Shield => 4,
+            Key::Helmet | Key::Boots => 2,
+            Key::Amulet => 1,
+            _ => 3,
+        }
     }
 }
 
@@ -60,3 +482,99 @@ impl fmt::Display for Equipment {
         write!(f, "{}[{}]", self.key(), self.level())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durability() {
+        let mut sword = Equipment::sword(10);
+        let full_strength = sword.strength();
+        assert_eq!(0, sword.repair_cost());
+
+        // no effect above half durability
+        for _ in 0..MAX_DURABILITY / 2 {
+            assert!(!sword.wear());
+        }
+        assert_eq!(full_strength, sword.strength());
+        assert_eq!(1250, sword.repair_cost());
+
+        for _ in 1..MAX_DURABILITY / 2 {
+            assert!(!sword.wear());
+        }
+        assert!(sword.strength() < full_strength);
+        assert!(sword.wear());
+
+        sword.repair();
+        assert_eq!(full_strength, sword.strength());
+
+        // only weapons and shields wear out
+        assert!(Equipment::shield(1).wears_out());
+        let boots = Equipment::boots(10).with_durability(1);
+        assert!(!boots.wears_out());
+        assert_eq!(0, boots.repair_cost());
+        assert_eq!(Equipment::boots(10).speed(), boots.speed());
+    }
+
+    #[test]
+    fn weapon_kinds() {
+        let sword = Equipment::sword(10);
+        let dagger = Equipment::dagger(10);
+        let staff = Equipment::staff(10);
+        let axe = Equipment::axe(10);
+
+        assert!(axe.strength() > sword.strength());
+        assert!(sword.strength() > dagger.strength());
+        assert!(dagger.strength() > staff.strength());
+
+        assert!(dagger.speed() > 0);
+        assert_eq!(0, sword.speed());
+        assert!(staff.magic_strength() > 0);
+        assert_eq!(0, axe.magic_strength());
+
+        let mage = Class::player_by_name("mage").unwrap();
+        assert_eq!(Key::Staff, Equipment::class_weapon(mage, 1).key());
+        let thief = Class::player_by_name("thief").unwrap();
+        assert_eq!(Key::Dagger, Equipment::class_weapon(thief, 1).key());
+    }
+
+    #[test]
+    fn rarity() {
+        let common = Equipment::sword(10);
+        assert_eq!(Rarity::Common, common.rarity());
+        assert!(!common.describe().contains("common"));
+
+        let rare = Equipment::sword(10).with_rarity(Rarity::Rare);
+        assert_eq!(2, rare.affixes.len());
+        assert_ne!(rare.affixes[0], rare.affixes[1]);
+        // the affixes are picked by the randomizer, deterministic in tests
+        assert_eq!(
+            rare.affixes,
+            Equipment::sword(1).with_rarity(Rarity::Rare).affixes
+        );
+        assert!(rare.describe().contains("; rare: "));
+        assert_eq!(common.strength(), rare.strength());
+
+        let unique = Equipment::sword(10).with_rarity(Rarity::Unique);
+        assert_eq!(3, unique.affixes.len());
+        assert!(unique.strength() > common.strength());
+
+        // affixes can make up for a lower level
+        assert!(rare.is_upgrade_from(&Some(common.clone())));
+        assert!(Equipment::sword(5)
+            .with_rarity(Rarity::Rare)
+            .is_upgrade_from(&Some(Equipment::sword(10))));
+        assert!(!Equipment::sword(5)
+            .with_rarity(Rarity::Magic)
+            .is_upgrade_from(&Some(Equipment::sword(10))));
+    }
+
+    #[test]
+    fn deserialize_legacy() {
+        // equipment used to be serialized as a [key, level] tuple
+        let sword: Equipment = serde_json::from_str(r#"["sword", 5]"#).unwrap();
+        assert_eq!(5, sword.level());
+        assert_eq!(MAX_DURABILITY, sword.durability());
+    }
+}
@@ -12,13 +12,30 @@ pub enum Key {
     Escape,
     Remedy,
     Ether,
+    Elixir,
+    Bomb,
+    SmokeBomb,
+    Antidote,
+    BurnHeal,
+    Warp,
+    Identify,
+    HolyWater,
+    Chest,
     HealthStone,
     MagicStone,
     PowerStone,
     SpeedStone,
     LevelStone,
     Sword,
+    Dagger,
+    Staff,
+    Axe,
+    Bow,
     Shield,
+    Helmet,
+    Armor,
+    Boots,
+    Amulet,
     Ring(Ring),
 }
 
@@ -29,8 +46,25 @@ impl Key {
             "ether" | "e" => Key::Ether,
             "remedy" | "r" => Key::Remedy,
             "escape" | "es" => Key::Escape,
+            "elixir" | "ex" => Key::Elixir,
+            "bomb" | "bm" => Key::Bomb,
+            "smoke-bomb" | "smoke" | "sb" => Key::SmokeBomb,
+            "antidote" | "an" => Key::Antidote,
+            "burn-heal" | "bh" => Key::BurnHeal,
+            "warp" | "wp" => Key::Warp,
+            "id-scroll" | "identify" | "id" => Key::Identify,
+            "holy-water" | "holy" | "hw" => Key::HolyWater,
+            "chest-key" | "key" | "ky" => Key::Chest,
             "sword" | "sw" => Key::Sword,
+            "dagger" | "dg" => Key::Dagger,
+            "staff" | "stf" => Key::Staff,
+            "axe" | "ax" => Key::Axe,
+            "bow" | "bw" => Key::Bow,
             "shield" | "sh" => Key::Shield,
+            "helmet" | "hm" => Key::Helmet,
+            "armor" | "ar" => Key::Armor,
+            "boots" | "bt" => Key::Boots,
+            "amulet" | "am" => Key::Amulet,
             "hp-stone" | "hp" => Key::HealthStone,
             "mp-stone" | "mp" => Key::MagicStone,
             "str-stone" | "str" | "strength" => Key::PowerStone,
@@ -64,6 +98,23 @@ impl Key {
         };
         Ok(key)
     }
+
+    /// Return true for the kinds of equipment that go in the weapon slot.
+    pub fn is_weapon(&self) -> bool {
+        matches!(
+            self,
+            Key::Sword | Key::Dagger | Key::Staff | Key::Axe | Key::Bow
+        )
+    }
+
+    /// Return true for the kinds of equipment that can be worn by the hero.
+    pub fn is_equipment(&self) -> bool {
+        self.is_weapon()
+            || matches!(
+                self,
+                Key::Shield | Key::Helmet | Key::Armor | Key::Boots | Key::Amulet
+            )
+    }
 }
 
 impl fmt::Display for Key {
@@ -73,13 +124,30 @@ impl fmt::Display for Key {
             Key::Escape => "escape",
             Key::Remedy => "remedy",
             Key::Ether => "ether",
+            Key::Elixir => "elixir",
+            Key::Bomb => "bomb",
+            Key::SmokeBomb => "smoke-bomb",
+            Key::Antidote => "antidote",
+            Key::BurnHeal => "burn-heal",
+            Key::Warp => "warp",
+            Key::Identify => "id-scroll",
+            Key::HolyWater => "holy-water",
+            Key::Chest => "chest-key",
             Key::HealthStone => "hp-stone",
             Key::MagicStone => "mp-stone",
             Key::PowerStone => "str-stone",
             Key::SpeedStone => "spd-stone",
             Key::LevelStone => "lvl-stone",
             Key::Sword => "sword",
+            Key::Dagger => "dagger",
+            Key::Staff => "staff",
+            Key::Axe => "axe",
+            Key::Bow => "bow",
             Key::Shield => "shield",
+            Key::Helmet => "helmet",
+            Key::Armor => "armor",
+            Key::Boots => "boots",
+            Key::Amulet => "amulet",
             Key::Ring(Ring::Void) => "void-rng",
             Key::Ring(Ring::Attack) => "att-rng",
             Key::Ring(Ring::Deffense) => "def-rng",
@@ -0,0 +1,526 @@
+use super::equipment::{Affix, Equipment, Rarity};
+use super::key::Key;
+use super::ring::Ring;
+use super::Item;
+use crate::character::Character;
+use crate::game::Game;
+use crate::log;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A named set of equipment pieces and rings that can be worn at once.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Loadout {
+    equipment: Vec<Piece>,
+    rings: Vec<Ring>,
+}
+
+/// The properties that tell a saved equipment piece apart from other ones
+/// of the same kind.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Piece {
+    key: Key,
+    level: i32,
+    #[serde(default)]
+    rarity: Rarity,
+    #[serde(default)]
+    affixes: Vec<Affix>,
+}
+
+impl Piece {
+    fn of(piece: &Equipment) -> Self {
+        Self {
+            key: piece.key(),
+            level: piece.level(),
+            rarity: piece.rarity(),
+            affixes: piece.affixes().to_vec(),
+        }
+    }
+
+    /// Return true if the given inventory piece can be worn in place of this
+    /// one. Unidentified and cursed pieces are never picked, since they
+    /// could hide a curse that prevents removing them.
+    fn matches(&self, piece: &Equipment) -> bool {
+        piece.is_identified() && piece.curse().is_none() && Self::of(piece) == *self
+    }
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.key, self.level)
+    }
+}
+
+/// The name of a hero stat with its value before and after a change.
+type StatChange = (&'static str, i32, i32);
+
+/// An item that can be worn by the hero.
+enum Wearable {
+    Piece(Equipment),
+    Ring(Ring),
+}
+
+impl Wearable {
+    fn name(&self) -> String {
+        match self {
+            Wearable::Piece(piece) => piece.to_string(),
+            Wearable::Ring(ring) => ring.to_string(),
+        }
+    }
+
+    /// Return a copy of the given character wearing this item.
+    fn worn_by(&self, player: &Character) -> Character {
+        let mut player = player.clone();
+        match self {
+            Wearable::Piece(piece) => {
+                player.equip(piece.clone());
+            }
+            Wearable::Ring(ring) => {
+                player.equip_ring(ring.clone());
+            }
+        }
+        player
+    }
+}
+
+/// Print the change in the hero stats of wearing the given item from the
+/// inventory, compared to the equipped one or to the other given item.
+pub fn compare(game: &Game, key: &Key, other: Option<&Key>) -> Result<()> {
+    let (name, other_name, stats) = comparison(game, key, other)?;
+    log::compare(&name, &other_name, &stats);
+    Ok(())
+}
+
+/// Return the names of the compared items and the hero stats before and
+/// after wearing the given item.
+fn comparison(
+    game: &Game,
+    key: &Key,
+    other: Option<&Key>,
+) -> Result<(String, String, Vec<StatChange>)> {
+    let Some(item) = from_inventory(game, key) else {
+        bail!("{} not found in the inventory.", key);
+    };
+
+    let (other_name, base) = match other {
+        Some(other) => {
+            let Some(other) = equipped(&game.player, other).or_else(|| from_inventory(game, other))
+            else {
+                bail!("{} not found.", other);
+            };
+            (other.name(), other.worn_by(&game.player))
+        }
+        None => {
+            // compare with whatever is in the same slot
+            let name = match key {
+                Key::Ring(_) => String::from("current rings"),
+                _ => game
+                    .player
+                    .equipped(key)
+                    .map_or(String::from("none"), |piece| piece.to_string()),
+            };
+            (name, game.player.clone())
+        }
+    };
+
+    let candidate = item.worn_by(&game.player);
+    let stats = vec![
+        ("att", base.physical_attack(), candidate.physical_attack()),
+        ("def", base.deffense(), candidate.deffense()),
+        ("spd", base.speed(), candidate.speed()),
+        ("hp", base.max_hp(), candidate.max_hp()),
+    ];
+    Ok((item.name(), other_name, stats))
+}
+
+/// Return the given item from the inventory, if it can be worn.
+fn from_inventory(game: &Game, key: &Key) -> Option<Wearable> {
+    let item = game.inventory.get(key)?.first()?;
+    if let Some(piece) = item.as_equipment() {
+        Some(Wearable::Piece(piece.clone()))
+    } else if let Key::Ring(ring) = key {
+        Some(Wearable::Ring(ring.clone()))
+    } else {
+        None
+    }
+}
+
+/// Return the item of the given kind currently worn by the hero, if any.
+fn equipped(player: &Character, key: &Key) -> Option<Wearable> {
+    match key {
+        Key::Ring(ring) if player.wears(ring.clone()) => Some(Wearable::Ring(ring.clone())),
+        _ => player
+            .equipped(key)
+            .filter(|piece| piece.key() == *key)
+            .map(|piece| Wearable::Piece(piece.clone())),
+    }
+}
+
+/// Remember the currently worn equipment and rings with the given name.
+pub fn save(game: &mut Game, name: &str) -> Result<()> {
+    let loadout = Loadout {
+        equipment: game
+            .player
+            .equipment()
+            .iter()
+            .map(|piece| Piece::of(piece))
+            .collect(),
+        rings: [&game.player.left_ring, &game.player.right_ring]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+    };
+    if loadout.equipment.is_empty() && loadout.rings.is_empty() {
+        bail!("Nothing equipped to save.");
+    }
+
+    game.loadouts.insert(name.to_string(), loadout);
+    log::loadout_saved(name);
+    Ok(())
+}
+
+/// Wear the equipment and rings of the loadout with the given name, moving
+/// the ones they replace to the inventory. If any of them is missing,
+/// nothing is changed.
+pub fn wear(game: &mut Game, name: &str) -> Result<()> {
+    let Some(loadout) = game.loadouts.get(name).cloned() else {
+        bail!("loadout {} not found.", name);
+    };
+
+    // cursed items can't be removed
+    let player = &game.player;
+    let removes_cursed = [&player.left_ring, &player.right_ring]
+        .into_iter()
+        .flatten()
+        .any(|ring| player.wears_cursed(ring) && !loadout.rings.contains(ring));
+    if removes_cursed && !loadout.rings.is_empty() {
+        bail!("equipped rings are cursed and can't be removed.");
+    }
+
+    let mut to_equip = Vec::new();
+    for piece in &loadout.equipment {
+        let worn = game
+            .player
+            .equipped(&piece.key)
+            .is_some_and(|worn| Piece::of(worn) == *piece);
+        if worn {
+            continue;
+        }
+        let Some(index) = find_piece(game, piece) else {
+            bail!("{} not found in the inventory.", piece);
+        };
+        if game.player.is_slot_cursed(&piece.key) {
+            bail!("the equipped piece is cursed and can't be removed.");
+        }
+        to_equip.push((&piece.key, index));
+    }
+    for ring in &loadout.rings {
+        let key = Key::Ring(ring.clone());
+        if !game.player.wears(ring.clone()) && !game.inventory.contains_key(&key) {
+            bail!("{} not found in the inventory.", key);
+        }
+    }
+
+    // the replaced items must fit in the inventory, besides the freed room
+    let mut weight = 0;
+    for (key, index) in &to_equip {
+        weight += game.player.equipped(key).map_or(0, |piece| piece.weight());
+        weight -= game.inventory[*key][*index].weight();
+    }
+    if !loadout.rings.is_empty() {
+        let worn = [&game.player.left_ring, &game.player.right_ring];
+        for ring in worn.into_iter().flatten() {
+            if !loadout.rings.contains(ring) {
+                weight += ring.weight();
+            }
+        }
+        for ring in &loadout.rings {
+            if !game.player.wears(ring.clone()) {
+                weight -= ring.weight();
+            }
+        }
+    }
+    if !game.has_room(weight) {
+        bail!("not enough room in the inventory for the replaced items.");
+    }
+
+    for (key, index) in to_equip {
+        let piece = take_piece(game, key, index);
+        if let Some(previous) = game.player.equip(piece) {
+            store(game, Box::new(previous));
+        }
+    }
+
+    if !loadout.rings.is_empty() {
+        for ring in [
+            game.player.left_ring.clone(),
+            game.player.right_ring.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if let Some(ring) = game.player.unequip_ring(&ring.key()) {
+                store(game, Box::new(ring));
+            }
+        }
+        // equip in reverse order so they end up in the same hands.
+        // cursed rings are kept on, so they don't need to be equipped again
+        for ring in loadout.rings.iter().rev() {
+            if game.player.wears(ring.clone()) {
+                continue;
+            }
+            game.take_item(&Key::Ring(ring.clone()));
+            game.player.equip_ring(ring.clone());
+        }
+    }
+
+    log::loadout_used(name);
+    Ok(())
+}
+
+/// Print the saved loadouts.
+pub fn list(game: &Game) {
+    let mut loadouts: Vec<(String, String)> = game
+        .loadouts
+        .iter()
+        .map(|(name, loadout)| {
+            let mut items: Vec<String> = loadout
+                .equipment
+                .iter()
+                .map(|piece| piece.to_string())
+                .collect();
+            items.extend(loadout.rings.iter().map(|ring| ring.to_string()));
+            (name.clone(), items.join(","))
+        })
+        .collect();
+    loadouts.sort();
+    log::loadout_list(loadouts);
+}
+
+/// Forget the loadout with the given name.
+pub fn delete(game: &mut Game, name: &str) -> Result<()> {
+    if game.loadouts.remove(name).is_none() {
+        bail!("loadout {} not found.", name);
+    }
+    Ok(())
+}
+
+/// Return the inventory index of the piece that best matches the saved one,
+/// preferring the least worn out if there are several.
+fn find_piece(game: &Game, piece: &Piece) -> Option<usize> {
+    game.inventory
+        .get(&piece.key)?
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            item.as_equipment()
+                .filter(|candidate| piece.matches(candidate))
+                .map(|candidate| (index, candidate.durability()))
+        })
+        .max_by_key(|(_, durability)| *durability)
+        .map(|(index, _)| index)
+}
+
+/// Remove the piece at the given index of the inventory entry for its kind.
+/// Should only be called with an index returned by `find_piece`.
+fn take_piece(game: &mut Game, key: &Key, index: usize) -> Equipment {
+    let items = game.inventory.get_mut(key).unwrap();
+    let piece = items.remove(index).as_equipment().cloned().unwrap();
+    if items.is_empty() {
+        game.inventory.remove(key);
+    }
+    piece
+}
+
+/// Move a replaced item to the inventory. This is a swap rather than a new
+/// item, so it doesn't trigger the item added events. The room for it
+/// should be checked beforehand.
+fn store(game: &mut Game, item: Box<dyn Item>) {
+    game.inventory.entry(item.key()).or_default().push(item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_wear() {
+        let mut game = Game::new();
+        game.player.sword = Some(Equipment::sword(5));
+        game.player.shield = Some(Equipment::shield(5));
+        game.ring_pool.remove(&Ring::Attack);
+        game.ring_pool.remove(&Ring::Deffense);
+        game.player.equip_ring(Ring::Attack);
+        assert!(save(&mut game, "boss").is_ok());
+
+        // swap to a different setup
+        game.add_item(Box::new(Equipment::sword(1)));
+        game.use_item(Key::Sword).unwrap();
+        game.use_item(Key::Ring(Ring::Attack)).unwrap();
+        game.add_item(Box::new(Ring::Deffense));
+        game.use_item(Key::Ring(Ring::Deffense)).unwrap();
+        assert_eq!(1, game.player.sword.as_ref().unwrap().level());
+
+        assert!(wear(&mut game, "boss").is_ok());
+        assert_eq!(5, game.player.sword.as_ref().unwrap().level());
+        assert_eq!(5, game.player.shield.as_ref().unwrap().level());
+        assert_eq!(Some(Ring::Attack), game.player.left_ring);
+        assert!(game.player.right_ring.is_none());
+        assert!(game.inventory().contains_key(&Key::Sword));
+        assert!(game.inventory().contains_key(&Key::Ring(Ring::Deffense)));
+
+        // missing pieces don't change anything
+        game.inventory.clear();
+        game.player.sword = Some(Equipment::sword(1));
+        assert!(wear(&mut game, "boss").is_err());
+        assert_eq!(1, game.player.sword.as_ref().unwrap().level());
+        assert!(wear(&mut game, "unknown").is_err());
+    }
+
+    #[test]
+    fn wear_full_inventory() {
+        let mut game = Game::new();
+        game.player.sword = Some(Equipment::sword(5));
+        game.ring_pool.clear();
+        game.player.equip_ring(Ring::Attack);
+        assert!(save(&mut game, "single").is_ok());
+
+        // swapping a piece for one of the same weight always fits
+        game.add_item(Box::new(Equipment::sword(1)));
+        game.use_item(Key::Sword).unwrap();
+        while game.has_room(1) {
+            game.add_item(Box::new(super::super::Potion::new(1)));
+        }
+        assert!(wear(&mut game, "single").is_ok());
+        assert_eq!(5, game.player.sword.as_ref().unwrap().level());
+
+        // but replacing two rings with one doesn't
+        game.player.equip_ring(Ring::Deffense);
+        game.player.equip_ring(Ring::Speed);
+        game.take_item(&Key::Potion);
+        game.add_item(Box::new(Ring::Attack));
+        assert!(!game.has_room(1));
+        assert!(wear(&mut game, "single").is_err());
+        assert_eq!(Some(Ring::Speed), game.player.left_ring);
+    }
+
+    #[test]
+    fn wear_cursed_rings() {
+        let mut game = Game::new();
+        game.ring_pool.clear();
+        game.player.equip_ring(Ring::Attack);
+        assert!(save(&mut game, "attack").is_ok());
+        let ring = game.player.unequip_ring(&Key::Ring(Ring::Attack)).unwrap();
+        game.add_item(Box::new(ring));
+
+        game.player.equip_ring(Ring::Speed);
+        game.player
+            .ring_curses
+            .insert(Ring::Speed, crate::item::curse::Curse::Drain);
+        game.player.equip_ring(Ring::Deffense);
+        assert!(save(&mut game, "cursed").is_ok());
+
+        // the cursed ring can't be removed
+        assert!(wear(&mut game, "attack").is_err());
+        assert!(game.player.wears(Ring::Deffense));
+
+        // but it can be kept on by the loadout
+        let ring = game
+            .player
+            .unequip_ring(&Key::Ring(Ring::Deffense))
+            .unwrap();
+        game.add_item(Box::new(ring));
+        assert!(wear(&mut game, "cursed").is_ok());
+        assert!(game.player.wears(Ring::Speed));
+        assert!(game.player.wears(Ring::Deffense));
+        assert!(game.inventory().contains_key(&Key::Ring(Ring::Attack)));
+    }
+
+    #[test]
+    fn compare_items() {
+        let mut game = Game::new();
+        assert!(compare(&game, &Key::Shield, None).is_err());
+
+        let player = game.player.clone();
+        let (att, def, spd, hp) = (
+            player.physical_attack(),
+            player.deffense(),
+            player.speed(),
+            player.max_hp(),
+        );
+        let shield_10 = Equipment::shield(10).strength();
+        let shield_5 = Equipment::shield(5).strength();
+
+        // compared to the empty slot
+        game.add_item(Box::new(Equipment::shield(10)));
+        let (_, other, stats) = comparison(&game, &Key::Shield, None).unwrap();
+        assert_eq!("none", other);
+        assert_eq!(
+            vec![
+                ("att", att, att),
+                ("def", def, def + shield_10),
+                ("spd", spd, spd),
+                ("hp", hp, hp),
+            ],
+            stats
+        );
+
+        // compared to the equipped piece
+        game.player.shield = Some(Equipment::shield(5));
+        let (_, _, stats) = comparison(&game, &Key::Shield, None).unwrap();
+        assert_eq!(("def", def + shield_5, def + shield_10), stats[1]);
+        assert_eq!(("att", att, att), stats[0]);
+
+        // compared to another item, wearing both
+        assert!(compare(&game, &Key::Shield, Some(&Key::Sword)).is_err());
+        game.add_item(Box::new(Equipment::sword(1)));
+        let (_, _, stats) = comparison(&game, &Key::Shield, Some(&Key::Sword)).unwrap();
+        let sword_1 = Equipment::sword(1).strength();
+        assert_eq!(("att", att + sword_1, att), stats[0]);
+        assert_eq!(("def", def + shield_5, def + shield_10), stats[1]);
+        assert_eq!(("spd", spd, spd), stats[2]);
+        assert_eq!(("hp", hp, hp), stats[3]);
+    }
+
+    #[test]
+    fn wear_matching_piece() {
+        let mut game = Game::new();
+        let saved = Equipment::sword(5).with_affixes(Rarity::Magic, vec![Affix::Swift]);
+        game.player.sword = Some(saved);
+        assert!(save(&mut game, "swift").is_ok());
+        game.player.sword = None;
+
+        // pieces of the same level but different properties aren't picked
+        game.add_item(Box::new(Equipment::sword(5)));
+        game.add_item(Box::new(
+            Equipment::sword(5)
+                .with_affixes(Rarity::Magic, vec![Affix::Swift])
+                .unidentified(None),
+        ));
+        assert!(wear(&mut game, "swift").is_err());
+        let mut cursed = Equipment::sword(5)
+            .with_affixes(Rarity::Magic, vec![Affix::Swift])
+            .unidentified(Some(crate::item::curse::Curse::Drain));
+        cursed.identify();
+        game.add_item(Box::new(cursed));
+        assert!(wear(&mut game, "swift").is_err());
+        assert!(game.player.sword.is_none());
+
+        game.add_item(Box::new(
+            Equipment::sword(5)
+                .with_affixes(Rarity::Magic, vec![Affix::Swift])
+                .with_durability(50),
+        ));
+        game.add_item(Box::new(
+            Equipment::sword(5).with_affixes(Rarity::Magic, vec![Affix::Swift]),
+        ));
+        assert!(wear(&mut game, "swift").is_ok());
+        let sword = game.player.sword.as_ref().unwrap();
+        assert!(sword.has_affix(Affix::Swift));
+        assert!(sword.curse().is_none());
+        assert_eq!(super::super::equipment::MAX_DURABILITY, sword.durability());
+    }
+}
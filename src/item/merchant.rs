@@ -0,0 +1,249 @@
+use super::equipment::Equipment;
+use super::key::Key;
+use super::{
+    Antidote, Bomb, BurnHeal, Elixir, Escape, Ether, HolyWater, Identify, Item, Potion, Remedy,
+    SmokeBomb, Warp,
+};
+use crate::character::Character;
+use crate::game::Game;
+use crate::location::Location;
+use crate::log;
+use crate::quest;
+use crate::randomizer::{random, Randomizer};
+use anyhow::{bail, Result};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Moves the hero can make before the merchant leaves.
+const STAY_MOVES: i32 = 10;
+
+/// How many kinds of items the merchant carries.
+const STOCK_KINDS: usize = 4;
+
+/// Percentage of the shop price charged by the merchant.
+const MARKUP_PERCENT: i32 = 150;
+
+/// A travelling merchant found away from home, that sells a few items
+/// at a premium while it stays at its location.
+#[derive(Serialize, Deserialize)]
+pub struct Merchant {
+    location: Location,
+    moves_left: i32,
+    stock: Vec<Box<dyn Item>>,
+}
+
+impl Merchant {
+    /// Count a move of the hero. Return false when it's time to leave.
+    fn stays(&mut self) -> bool {
+        self.moves_left -= 1;
+        self.moves_left > 0
+    }
+
+    fn cost(item: &dyn Item) -> i32 {
+        item.as_shoppable().map_or(0, |shoppable| shoppable.cost()) * MARKUP_PERCENT / 100
+    }
+}
+
+/// Update the merchant after a move of the hero, maybe making a new one
+/// appear at the current location if `can_appear` is true.
+pub fn moved(game: &mut Game, can_appear: bool) {
+    if let Some(merchant) = &mut game.merchant {
+        if !merchant.stays() {
+            game.merchant = None;
+        }
+        return;
+    }
+
+    let distance = game.location.distance_from_home();
+    if can_appear && random().merchant_appears(&distance) {
+        game.merchant = Some(Merchant {
+            location: game.location.clone(),
+            moves_left: STAY_MOVES,
+            stock: random_stock(&game.player),
+        });
+        log::merchant_appears(STAY_MOVES);
+    }
+}
+
+/// Return true if the merchant is at the hero's location.
+pub fn is_here(game: &Game) -> bool {
+    game.merchant
+        .as_ref()
+        .is_some_and(|merchant| merchant.location == game.location)
+}
+
+/// Print the items carried by the merchant and their price.
+pub fn list(game: &Game) -> Result<()> {
+    let Some(merchant) = game.merchant.as_ref().filter(|_| is_here(game)) else {
+        bail!("There's no merchant here.");
+    };
+
+    let mut counts: HashMap<Key, (i32, String, i32)> = HashMap::new();
+    for item in &merchant.stock {
+        let entry = counts.entry(item.key()).or_insert((
+            Merchant::cost(item.as_ref()),
+            item.to_string(),
+            0,
+        ));
+        entry.2 += 1;
+    }
+    let mut items: Vec<_> = counts.into_values().collect();
+    items.sort();
+    log::shop_list(game, items);
+    log::merchant_leaves(merchant.moves_left);
+    Ok(())
+}
+
+/// Buy the given items from the merchant. Like the shop, it stops at the
+/// first error but keeps what was bought so far.
+pub fn buy(game: &mut Game, item_keys: &[Key]) -> Result<()> {
+    if !is_here(game) {
+        bail!("There's no merchant here.");
+    }
+
+    let mut item_counts = HashMap::new();
+    let mut total_cost = 0;
+    let mut error = String::from("");
+
+    for key in item_keys {
+        let stock = &game.merchant.as_ref().unwrap().stock;
+        let Some(index) = stock.iter().position(|item| item.key() == *key) else {
+            error = format!("{} not available.", key);
+            break;
+        };
+
+        let cost = Merchant::cost(stock[index].as_ref());
+        if game.gold < cost {
+            error = "Not enough gold.".to_string();
+            break;
+        }
+        if key.is_equipment() && game.player.is_slot_cursed(key) {
+            error = "The equipped piece is cursed and can't be removed.".to_string();
+            break;
+        }
+        if !key.is_equipment() && !game.has_room(1) {
+            error = "Inventory is full.".to_string();
+            break;
+        }
+
+        let item = game.merchant.as_mut().unwrap().stock.remove(index);
+        game.gold -= cost;
+        item.as_shoppable().unwrap().add_to(game);
+        total_cost += cost;
+        *item_counts.entry(key.clone()).or_insert(0) += 1;
+        quest::item_bought(game, key.clone());
+    }
+
+    log::shop_buy(total_cost, &item_counts);
+    if total_cost > 0 {
+        quest::gold_spent(game, total_cost);
+    }
+    if !error.is_empty() {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Pick a few kinds of shop items, with a handful of units each.
+fn random_stock(player: &Character) -> Vec<Box<dyn Item>> {
+    let level = player.rounded_level();
+    let mut rng = rand::thread_rng();
+    let mut kinds: Vec<Box<dyn Fn() -> Box<dyn Item>>> = vec![
+        Box::new(move || Box::new(Potion::new(level))),
+        Box::new(move || Box::new(Ether::new(level))),
+        Box::new(|| Box::new(Remedy::new())),
+        Box::new(|| Box::new(Escape::new())),
+        Box::new(|| Box::new(Antidote::new())),
+        Box::new(|| Box::new(BurnHeal::new())),
+        Box::new(move || Box::new(Bomb::new(level))),
+        Box::new(|| Box::new(SmokeBomb::new())),
+        Box::new(|| Box::new(Warp::new())),
+        Box::new(|| Box::new(Elixir::new())),
+        Box::new(|| Box::new(Identify::new())),
+        Box::new(|| Box::new(HolyWater::new())),
+    ];
+    kinds.shuffle(&mut rng);
+
+    let mut stock: Vec<Box<dyn Item>> = Vec::new();
+    for kind in kinds.iter().take(STOCK_KINDS) {
+        for _ in 0..rng.gen_range(1..=3) {
+            stock.push(kind());
+        }
+    }
+
+    // and a single piece of equipment the hero can use, with the weapon
+    // matching the hero's class
+    let pieces: Vec<Equipment> = [
+        Equipment::class_weapon(&player.class, level),
+        Equipment::shield(level),
+        Equipment::helmet(level),
+        Equipment::armor(level),
+        Equipment::boots(level),
+        Equipment::amulet(level),
+    ]
+    .into_iter()
+    .filter(|piece| player.can_use(&piece.key()))
+    .collect();
+    let piece = pieces.choose(&mut rng).unwrap().clone();
+    stock.push(Box::new(piece));
+    stock
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merchant_at(game: &mut Game) {
+        game.merchant = Some(Merchant {
+            location: game.location.clone(),
+            moves_left: 2,
+            stock: vec![
+                Box::new(Potion::new(1)),
+                Box::new(Potion::new(1)),
+                Box::new(Equipment::shield(1)),
+            ],
+        });
+    }
+
+    #[test]
+    fn buy_from_merchant() {
+        let mut game = Game::new();
+        assert!(buy(&mut game, &[Key::Potion]).is_err());
+
+        merchant_at(&mut game);
+        game.gold = 500;
+        assert!(buy(&mut game, &[Key::Potion, Key::Potion]).is_err());
+        assert_eq!(200, game.gold);
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+
+        game.gold = 1000;
+        assert!(buy(&mut game, &[Key::Shield, Key::Potion]).is_err());
+        assert_eq!(250, game.gold);
+        assert!(game.player.shield.is_some());
+    }
+
+    #[test]
+    fn class_weapon_stock() {
+        let mut game = Game::new();
+        assert!(game.player.change_class("thief").is_ok());
+        for _ in 0..20 {
+            let stock = random_stock(&game.player);
+            assert!(!stock.iter().any(|item| item.key() == Key::Sword));
+            assert!(!stock.iter().any(|item| item.key() == Key::Helmet));
+        }
+    }
+
+    #[test]
+    fn merchant_leaves() {
+        let mut game = Game::new();
+        merchant_at(&mut game);
+        assert!(is_here(&game));
+
+        moved(&mut game, true);
+        assert!(is_here(&game));
+        moved(&mut game, true);
+        assert!(game.merchant.is_none());
+    }
+}
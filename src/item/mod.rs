@@ -1,16 +1,23 @@
 use core::fmt;
 
 use crate::character::class as character;
+use crate::character::StatusEffect;
 use crate::game;
 use crate::location;
 use crate::log;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
 pub mod chest;
+pub mod craft;
+pub mod curse;
 pub mod equipment;
 pub mod key;
+pub mod loadout;
+pub mod merchant;
 pub mod ring;
 pub mod shop;
+pub mod stash;
 pub mod stone;
 
 #[typetag::serde(tag = "type")]
@@ -18,6 +25,61 @@ pub trait Item: fmt::Display {
     fn apply(&mut self, game: &mut game::Game);
     fn key(&self) -> key::Key;
     fn describe(&self) -> String;
+
+    /// Return the equipment piece if this item is one, e.g. to compare it
+    /// with the one currently equipped.
+    fn as_equipment(&self) -> Option<&equipment::Equipment> {
+        None
+    }
+
+    /// Return the item as sold by the shop, if it is, to calculate its
+    /// value when selling it back.
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        None
+    }
+
+    /// How much of the hero's carrying capacity the item takes.
+    fn weight(&self) -> i32 {
+        1
+    }
+
+    /// Return an error if the item can't be used in the current game
+    /// state, in which case it's kept in the inventory.
+    fn check_use(&self, _game: &game::Game) -> Result<()> {
+        Ok(())
+    }
+
+    /// Damage inflicted when the item is thrown at an enemy during battle,
+    /// if it's that kind of item.
+    fn throw_damage(&self) -> Option<i32> {
+        None
+    }
+}
+
+/// Build a new item of the given kind, for the ones that only need a level
+/// to be created, e.g. for crafting results.
+pub fn from_key(key: &key::Key, level: i32) -> Option<Box<dyn Item>> {
+    let item: Box<dyn Item> = match key {
+        key::Key::Potion => Box::new(Potion::new(level)),
+        key::Key::Ether => Box::new(Ether::new(level)),
+        key::Key::Remedy => Box::new(Remedy::new()),
+        key::Key::Escape => Box::new(Escape::new()),
+        key::Key::Elixir => Box::new(Elixir::new()),
+        key::Key::Bomb => Box::new(Bomb::new(level)),
+        key::Key::SmokeBomb => Box::new(SmokeBomb::new()),
+        key::Key::Antidote => Box::new(Antidote::new()),
+        key::Key::BurnHeal => Box::new(BurnHeal::new()),
+        key::Key::Warp => Box::new(Warp::new()),
+        key::Key::Identify => Box::new(Identify::new()),
+        key::Key::HolyWater => Box::new(HolyWater::new()),
+        key::Key::HealthStone => Box::new(stone::Health),
+        key::Key::MagicStone => Box::new(stone::Magic),
+        key::Key::PowerStone => Box::new(stone::Power),
+        key::Key::SpeedStone => Box::new(stone::Speed),
+        key::Key::LevelStone => Box::new(stone::Level),
+        _ => return None,
+    };
+    Some(item)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -55,6 +117,10 @@ impl Item for Potion {
     fn describe(&self) -> String {
         format!("restores {}hp", self.restores())
     }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -79,6 +145,10 @@ impl Item for Escape {
     fn describe(&self) -> String {
         String::from("transports the player safely back home")
     }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
 }
 
 impl fmt::Display for Escape {
@@ -110,6 +180,10 @@ impl Item for Remedy {
     fn describe(&self) -> String {
         String::from("removes status ailments")
     }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
 }
 
 impl fmt::Display for Remedy {
@@ -156,4 +230,360 @@ impl Item for Ether {
     fn describe(&self) -> String {
         format!("restores level {} amount mp", self.level)
     }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Elixir {}
+
+impl Elixir {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[typetag::serde]
+impl Item for Elixir {
+    fn apply(&mut self, game: &mut game::Game) {
+        let healed = game.player.status_effect.take().is_some();
+        let recovered = game.player.update_hp(game.player.max_hp()).unwrap();
+        let recovered_mp = game.player.update_mp(game.player.max_mp());
+        log::heal_item(&game.player, "elixir", recovered, recovered_mp, healed);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Elixir
+    }
+
+    fn describe(&self) -> String {
+        String::from("restores all hp and mp and removes status ailments")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+}
+
+impl fmt::Display for Elixir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "elixir")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bomb {
+    level: i32,
+}
+
+impl Bomb {
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+
+    fn damage(&self) -> i32 {
+        character::Class::player_first().strength.at(self.level) * 2
+    }
+}
+
+impl fmt::Display for Bomb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bomb[{}]", self.level)
+    }
+}
+
+#[typetag::serde]
+impl Item for Bomb {
+    /// Bombs are thrown at the enemy during battle, see `throw_damage`
+    /// and `Game::bomb_battle`.
+    fn apply(&mut self, _game: &mut game::Game) {}
+
+    fn key(&self) -> key::Key {
+        key::Key::Bomb
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "deals {} damage to a tough enemy, ignoring its deffense",
+            self.damage()
+        )
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
+    fn check_use(&self, _game: &game::Game) -> Result<()> {
+        bail!("bombs can only be thrown at enemies.")
+    }
+
+    fn throw_damage(&self) -> Option<i32> {
+        Some(self.damage())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmokeBomb {}
+
+impl SmokeBomb {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for SmokeBomb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "smoke-bomb")
+    }
+}
+
+#[typetag::serde]
+impl Item for SmokeBomb {
+    /// Smoke bombs are used when running away from a battle.
+    fn apply(&mut self, _game: &mut game::Game) {}
+
+    fn key(&self) -> key::Key {
+        key::Key::SmokeBomb
+    }
+
+    fn describe(&self) -> String {
+        String::from("guarantees running away from a battle with --run")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
+    fn check_use(&self, _game: &game::Game) -> Result<()> {
+        bail!("smoke bombs are used when running away from battle.")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Antidote {}
+
+impl Antidote {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for Antidote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "antidote")
+    }
+}
+
+#[typetag::serde]
+impl Item for Antidote {
+    fn apply(&mut self, game: &mut game::Game) {
+        let healed = game.player.cure(StatusEffect::Poison);
+        log::heal_item(&game.player, "antidote", 0, 0, healed);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Antidote
+    }
+
+    fn describe(&self) -> String {
+        String::from("cures poison")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BurnHeal {}
+
+impl BurnHeal {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for BurnHeal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "burn-heal")
+    }
+}
+
+#[typetag::serde]
+impl Item for BurnHeal {
+    fn apply(&mut self, game: &mut game::Game) {
+        let healed = game.player.cure(StatusEffect::Burn);
+        log::heal_item(&game.player, "burn-heal", 0, 0, healed);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::BurnHeal
+    }
+
+    fn describe(&self) -> String {
+        String::from("cures burns")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Warp {}
+
+impl Warp {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for Warp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warp")
+    }
+}
+
+#[typetag::serde]
+impl Item for Warp {
+    fn apply(&mut self, game: &mut game::Game) {
+        if let Some(location) = game.last_tombstone() {
+            // status effects are checked before use, so the hero can't die here
+            game.visit(location).unwrap_or_default();
+        }
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Warp
+    }
+
+    fn describe(&self) -> String {
+        String::from("transports the player safely to the last tombstone")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
+    fn check_use(&self, game: &game::Game) -> Result<()> {
+        if game.last_tombstone().is_none() {
+            bail!("no tombstone to warp to.");
+        }
+        // the status effect could kill the hero on arrival
+        if let Some(status) = game.player.status_effect {
+            bail!("can't warp while suffering from {}.", status);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Identify {}
+
+impl Identify {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for Identify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "id-scroll")
+    }
+}
+
+#[typetag::serde]
+impl Item for Identify {
+    fn apply(&mut self, game: &mut game::Game) {
+        let identified = game.identify_all();
+        log::identified(&identified, 0);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Identify
+    }
+
+    fn describe(&self) -> String {
+        String::from("identifies the carried items, revealing their curses")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HolyWater {}
+
+impl HolyWater {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for HolyWater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "holy-water")
+    }
+}
+
+#[typetag::serde]
+impl Item for HolyWater {
+    fn apply(&mut self, game: &mut game::Game) {
+        game.player.purify();
+        log::purified();
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::HolyWater
+    }
+
+    fn describe(&self) -> String {
+        String::from("removes the curses of the equipped items")
+    }
+
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
+    fn check_use(&self, game: &game::Game) -> Result<()> {
+        if game.player.curses().is_empty() {
+            bail!("no cursed items equipped.");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChestKey {}
+
+impl ChestKey {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl fmt::Display for ChestKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "chest-key")
+    }
+}
+
+#[typetag::serde]
+impl Item for ChestKey {
+    fn apply(&mut self, _game: &mut game::Game) {}
+
+    fn key(&self) -> key::Key {
+        key::Key::Chest
+    }
+
+    fn describe(&self) -> String {
+        String::from("opens a locked chest")
+    }
+
+    fn check_use(&self, _game: &game::Game) -> Result<()> {
+        bail!("keys are used when inspecting a locked chest.")
+    }
 }
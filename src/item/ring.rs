@@ -1,5 +1,7 @@
 use super::{key, Item};
 use crate::game;
+use crate::log;
+use anyhow::{bail, Result};
 use core::fmt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -33,15 +35,19 @@ pub enum Ring {
     Diamond,
 }
 
+/// Highest level a ring can be upgraded to.
+pub const MAX_LEVEL: i32 = 5;
+
 impl Ring {
     pub fn set() -> HashSet<Ring> {
         Ring::iter().collect()
     }
 
     /// For stat modifying stats, return the factor that should be
-    /// applied to the base character stat.
-    pub fn factor(&self) -> f64 {
-        match self {
+    /// applied to the base character stat. Each level above the first
+    /// increases it by 20%.
+    pub fn factor(&self, level: i32) -> f64 {
+        let base = match self {
             Ring::Attack => 0.5,
             Ring::Deffense => 0.5,
             Ring::Speed => 0.5,
@@ -49,10 +55,53 @@ impl Ring {
             Ring::MP => 0.5,
             Ring::HP => 0.5,
             _ => 0.0,
+        };
+        base * (1.0 + 0.2 * (level - 1) as f64)
+    }
+
+    /// Return true for the rings whose effect improves with their level.
+    pub fn is_upgradable(&self) -> bool {
+        self.factor(1) > 0.0
+    }
+}
+
+/// Pairs of rings that produce an extra effect when worn together.
+#[derive(Clone, Copy, PartialEq, Eq, EnumIter, Debug)]
+pub enum RingSet {
+    FireSpells,
+    Flurry,
+    TreasureHunter,
+}
+
+impl RingSet {
+    pub fn rings(&self) -> (Ring, Ring) {
+        match self {
+            RingSet::FireSpells => (Ring::Fire, Ring::Magic),
+            RingSet::Flurry => (Ring::Double, Ring::Counter),
+            RingSet::TreasureHunter => (Ring::Gold, Ring::Chest),
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            RingSet::FireSpells => "magic attacks always burn",
+            RingSet::Flurry => "counter-attacks strike twice",
+            RingSet::TreasureHunter => "chests always contain gold",
         }
     }
 }
 
+impl fmt::Display for RingSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RingSet::FireSpells => "fire-spells",
+            RingSet::Flurry => "flurry",
+            RingSet::TreasureHunter => "treasure-hunter",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl fmt::Display for Ring {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.key())
@@ -64,9 +113,25 @@ impl Item for Ring {
     /// When the ring is used, equip in the player. If the player was already
     /// wearing two rings, move the second one back to the inventory.
     fn apply(&mut self, game: &mut game::Game) {
+        if let Some(curse) = game.player.ring_curses.get(self) {
+            log::cursed(&self.to_string(), *curse);
+        }
         if let Some(removed) = game.player.equip_ring(self.clone()) {
-            game.add_item(Box::new(removed));
+            game.add_or_leave(Box::new(removed));
+        }
+    }
+
+    fn check_use(&self, game: &game::Game) -> Result<()> {
+        let player = &game.player;
+        let cursed = [&player.left_ring, &player.right_ring]
+            .into_iter()
+            .flatten()
+            .filter(|ring| player.wears_cursed(ring))
+            .count();
+        if cursed == 2 {
+            bail!("equipped rings are cursed and can't be removed.");
         }
+        Ok(())
     }
 
     fn key(&self) -> key::Key {
@@ -1,27 +1,219 @@
 use std::fmt::Display;
 
-use super::equipment::Equipment;
+use super::curse;
+use super::equipment::{Equipment, Rarity};
 use super::key::Key;
 use super::ring::Ring;
+use super::stone;
 use super::Item;
 use crate::character::Character;
-use crate::game::Game;
+use crate::game::{now, Game};
 use crate::log;
 use crate::quest;
+use crate::randomizer::{random, Randomizer};
 use anyhow::{bail, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use rand::seq::{IteratorRandom, SliceRandom};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Percentage of the shop price paid for items sold back to it.
+const SELL_PERCENT: i32 = 40;
+
+/// Units of each consumable available after a restock.
+const ITEM_STOCK: i32 = 5;
+
+/// Units of each equipment piece and ring available after a restock.
+const GEAR_STOCK: i32 = 1;
+
+/// Seconds between restocks.
+const RESTOCK_SECONDS: u64 = 24 * 60 * 60;
+
+/// Battles won that also trigger a restock, if before the time interval.
+const RESTOCK_BATTLES: i32 = 20;
+
+/// Maximum amount of special offers after a restock.
+const MAX_SPECIALS: usize = 3;
+
+/// Percentage of the base price charged for special offers.
+const SPECIAL_MARKUP_PERCENT: i32 = 150;
+
+/// An item sold at the shop, as defined in the catalog.
+#[derive(Deserialize, Debug, Clone)]
+struct Entry {
+    item: Key,
+
+    /// Hero level required for the item to be offered.
+    #[serde(default)]
+    min_level: i32,
+
+    price: Price,
+
+    /// Units available after a restock, if different from the default.
+    stock: Option<i32>,
+
+    #[serde(default)]
+    discounts: Vec<Discount>,
+}
+
+/// Gold charged for an item: a base amount plus an amount per level.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct Price {
+    base: i32,
+    per_level: i32,
+}
+
+/// Percentage off the price for heroes of the given class or that
+/// completed the given quest. If both are set, both must match.
+#[derive(Deserialize, Debug, Clone)]
+struct Discount {
+    class: Option<String>,
+    quest: Option<String>,
+    percent: i32,
+}
+
+static CATALOG: OnceCell<Vec<Entry>> = OnceCell::new();
+
+static DEFAULT_CATALOG: Lazy<Vec<Entry>> =
+    Lazy::new(|| serde_yaml::from_slice(include_bytes!("shop.yaml")).unwrap());
+
+/// Customize the shop catalog based on an input yaml byte array.
+pub fn load(bytes: &[u8]) {
+    CATALOG.set(serde_yaml::from_slice(bytes).unwrap()).unwrap();
+}
+
+fn catalog() -> &'static Vec<Entry> {
+    CATALOG.get().unwrap_or(&DEFAULT_CATALOG)
+}
+
+/// Find the catalog entry of the given item.
+fn entry(key: &Key) -> Option<&'static Entry> {
+    catalog().iter().find(|entry| entry.item == *key)
+}
+
+impl Entry {
+    fn price(&self, level: i32) -> i32 {
+        self.price.base + self.price.per_level * level
+    }
+
+    /// The biggest of the discounts that apply to the hero; they don't add up.
+    fn discount(&self, game: &Game) -> i32 {
+        self.discounts
+            .iter()
+            .filter(|discount| discount.applies(game))
+            .map(|discount| discount.percent)
+            .max()
+            .unwrap_or(0)
+            .clamp(0, 100)
+    }
+}
+
+impl Discount {
+    fn applies(&self, game: &Game) -> bool {
+        self.class
+            .as_ref()
+            .is_none_or(|class| *class == game.player.class.name)
+            && self
+                .quest
+                .as_ref()
+                .is_none_or(|quest| game.quests.is_completed(quest))
+    }
+}
+
+/// The shop at home. It has limited stock of the regular items, which
+/// depend on the hero level, and a few special offers, all of them
+/// replenished periodically.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Shop {
+    /// Units of each regular item sold since the last restock.
+    sold: HashMap<Key, i32>,
+    specials: Vec<Special>,
+
+    /// Unix timestamp of the last restock.
+    restocked_at: u64,
+
+    /// Battles won since the last restock.
+    battles: i32,
+}
+
+/// An item offered once until the next restock, at a premium.
+#[derive(Serialize, Deserialize)]
+struct Special {
+    item: Box<dyn Item>,
+    cost: i32,
+}
+
+impl Shop {
+    /// Count a won battle towards the next restock.
+    pub fn battle_won(&mut self) {
+        self.battles += 1;
+    }
+
+    /// Units left of the given regular item.
+    fn stock(&self, key: &Key) -> i32 {
+        let initial = entry(key)
+            .and_then(|entry| entry.stock)
+            .unwrap_or(match key {
+                key if key.is_equipment() => GEAR_STOCK,
+                Key::Ring(_) => GEAR_STOCK,
+                _ => ITEM_STOCK,
+            });
+        initial - self.sold.get(key).copied().unwrap_or(0)
+    }
+
+    fn needs_restock(&self, now: u64) -> bool {
+        now >= self.restocked_at + RESTOCK_SECONDS || self.battles >= RESTOCK_BATTLES
+    }
+
+    /// Seconds and battles left until the next restock, whatever comes first.
+    fn next_restock(&self, now: u64) -> (u64, i32) {
+        (
+            (self.restocked_at + RESTOCK_SECONDS).saturating_sub(now),
+            RESTOCK_BATTLES - self.battles,
+        )
+    }
+}
+
+/// Refill the shop stock and pick new special offers if it's time to.
+fn restock(game: &mut Game) {
+    let now = now();
+    if !game.shop.needs_restock(now) {
+        return;
+    }
+
+    game.shop = Shop {
+        specials: random_specials(game),
+        restocked_at: now,
+        ..Shop::default()
+    };
+}
+
 /// Print the list of available items and their price.
-pub fn list(game: &Game) -> Result<()> {
+pub fn list(game: &mut Game) -> Result<()> {
     if !game.location.is_home() {
         bail!("Shop is only allowed at home.");
     }
 
+    restock(game);
     let items = available_items(&game.player)
         .iter()
-        .map(|s| (s.cost(), s.to_string()))
+        .map(|s| {
+            (
+                shop_cost(game, s.as_ref()),
+                s.to_string(),
+                game.shop.stock(&s.to_key()),
+            )
+        })
         .collect();
-    log::shop_list(game, items);
+    let specials = game
+        .shop
+        .specials
+        .iter()
+        .map(|special| (special.cost, special.item.to_string(), 1))
+        .collect();
+    log::shop_stock(game, items, specials, game.shop.next_restock(now()));
     Ok(())
 }
 
@@ -34,6 +226,7 @@ pub fn buy(game: &mut Game, item_keys: &[Key]) -> Result<()> {
         bail!("Shop is only allowed at home.");
     }
 
+    restock(game);
     let mut item_counts = HashMap::new();
     let mut total_cost = 0;
     let mut error = String::from("");
@@ -43,21 +236,52 @@ pub fn buy(game: &mut Game, item_keys: &[Key]) -> Result<()> {
         // get list every time to prevent e.g. buying the sword twice
         let item = available_items(&game.player)
             .into_iter()
-            .find(|s| s.to_key() == *key);
+            .find(|s| s.to_key() == *key && game.shop.stock(key) > 0);
+
+        if item.is_none() {
+            match buy_special(game, key) {
+                Ok(Some(cost)) => {
+                    total_cost += cost;
+                    *item_counts.entry(key.clone()).or_insert(0) += 1;
+                    continue;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    error = err.to_string();
+                    break;
+                }
+            }
+        }
 
         if let Some(item) = item {
-            let item_cost = item.cost();
+            let item_cost = shop_cost(game, item.as_ref());
 
             if game.gold < item_cost {
                 error = "Not enough gold.".to_string();
                 break;
             }
+            if key.is_equipment() && game.player.is_slot_cursed(key) {
+                error = "The equipped piece is cursed and can't be removed.".to_string();
+                break;
+            }
+            // equipment is worn right away, so it takes no room
+            if !key.is_equipment() && !game.has_room(1) {
+                error = "Inventory is full.".to_string();
+                break;
+            }
             game.gold -= item_cost;
             item.add_to(game);
+            *game.shop.sold.entry(key.clone()).or_insert(0) += 1;
 
             total_cost += item_cost;
             *item_counts.entry(key.clone()).or_insert(0) += 1;
             quest::item_bought(game, item.to_key());
+        } else if available_items(&game.player)
+            .iter()
+            .any(|s| s.to_key() == *key)
+        {
+            error = format!("{} is out of stock.", key);
+            break;
         } else {
             error = format!("{} not available.", key);
             break;
@@ -66,62 +290,321 @@ pub fn buy(game: &mut Game, item_keys: &[Key]) -> Result<()> {
 
     // log what could be bought even if there was an error
     log::shop_buy(total_cost, &item_counts);
+    if total_cost > 0 {
+        quest::gold_spent(game, total_cost);
+    }
     if !error.is_empty() {
         bail!(error);
     }
     Ok(())
 }
 
-/// Build a list of items currently available at the shop
-fn available_items(player: &Character) -> Vec<Box<dyn Shoppable>> {
-    let mut items = Vec::<Box<dyn Shoppable>>::new();
-    let level = player.rounded_level();
+/// Buy the special offer of the given kind, if there's one.
+/// Return the gold paid, or None if there's no such offer.
+fn buy_special(game: &mut Game, key: &Key) -> Result<Option<i32>> {
+    let Some(index) = game
+        .shop
+        .specials
+        .iter()
+        .position(|special| special.item.key() == *key)
+    else {
+        return Ok(None);
+    };
+
+    let special = &game.shop.specials[index];
+    if game.gold < special.cost {
+        bail!("Not enough gold.");
+    }
+    if !game.has_room(special.item.weight()) {
+        bail!("Inventory is full.");
+    }
+    if let Key::Ring(ring) = key {
+        // the ring may have been found in a chest in the meantime
+        if game.ring_pool.take(ring).is_none() {
+            game.shop.specials.remove(index);
+            return Ok(None);
+        }
+        game.player.ring_curses.remove(ring);
+        game.player.unidentified_rings.remove(ring);
+    }
 
-    let sword = Equipment::sword(level);
-    if sword.is_upgrade_from(&player.sword) {
-        items.push(Box::new(sword));
+    let special = game.shop.specials.remove(index);
+    game.gold -= special.cost;
+    game.add_or_leave(special.item);
+    quest::item_bought(game, key.clone());
+    Ok(Some(special.cost))
+}
+
+/// Pick a few random items to offer until the next restock:
+/// stones, rings not found yet and gear above the hero level.
+fn random_specials(game: &Game) -> Vec<Special> {
+    let mut rng = rand::thread_rng();
+    let level = game.player.rounded_level() + 5;
+    let gear = [
+        Equipment::class_weapon(&game.player.class, level),
+        Equipment::shield(level),
+        Equipment::helmet(level),
+        Equipment::armor(level),
+        Equipment::boots(level),
+        Equipment::amulet(level),
+    ]
+    .choose(&mut rng)
+    .unwrap()
+    .clone()
+    .with_rarity(Rarity::Rare);
+
+    let mut candidates: Vec<(Box<dyn Item>, i32)> = vec![
+        (Box::new(stone::Health), 2_500),
+        (Box::new(stone::Magic), 2_500),
+        (Box::new(stone::Power), 2_500),
+        (Box::new(stone::Speed), 2_500),
+        (Box::new(stone::Level), 10_000),
+    ];
+    candidates.push((Box::new(gear.clone()), gear.cost()));
+    if let Some(ring) = game.ring_pool.iter().choose(&mut rng) {
+        candidates.push((Box::new(ring.clone()), 15_000));
     }
+    candidates.shuffle(&mut rng);
+
+    candidates
+        .into_iter()
+        .filter(|_| random().shop_special())
+        .take(MAX_SPECIALS)
+        .map(|(item, cost)| Special {
+            item,
+            cost: cost * SPECIAL_MARKUP_PERCENT / 100,
+        })
+        .collect()
+}
 
-    let shield = Equipment::shield(level);
-    if shield.is_upgrade_from(&player.shield) {
-        items.push(Box::new(shield));
+/// Restore the durability of the hero's equipment, charging according
+/// to the level and how worn out each piece is.
+/// Like shopping, repairs are only allowed at home.
+pub fn repair(game: &mut Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Repairs are only allowed at home.");
     }
 
-    let potion = super::Potion::new(level);
-    items.push(Box::new(potion));
+    let cost = game
+        .player
+        .equipment()
+        .iter()
+        .map(|equipment| equipment.repair_cost())
+        .sum();
 
-    let ether = super::Ether::new(level);
-    items.push(Box::new(ether));
+    if game.gold < cost {
+        bail!("Not enough gold.");
+    }
 
-    let remedy = super::Remedy::new();
-    items.push(Box::new(remedy));
+    game.gold -= cost;
+    for equipment in game.player.equipment_slots().into_iter().flatten() {
+        equipment.repair();
+    }
 
-    let escape = super::Escape::new();
-    items.push(Box::new(escape));
+    log::repair(cost);
+    if cost > 0 {
+        quest::gold_spent(game, cost);
+    }
+    Ok(())
+}
 
-    if player.level >= 25 {
-        items.push(Box::new(Ring::Diamond));
+/// Print the items in the inventory and the price the shop pays for them.
+pub fn sell_list(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Shop is only allowed at home.");
     }
 
+    log::shop_list(game, sell_rows(game));
+    Ok(())
+}
+
+/// The price, name and count of the items in the inventory. Items of
+/// the same kind with different levels, e.g. potion[1] and potion[10],
+/// get separate rows since the shop pays a different price for them.
+fn sell_rows(game: &Game) -> Vec<(i32, String, i32)> {
+    let mut counts: HashMap<(i32, String), i32> = HashMap::new();
+    for item in game.inventory.values().flatten() {
+        let name = if game.is_identified(item.as_ref()) {
+            item.to_string()
+        } else {
+            curse::unknown_name(item.as_ref())
+        };
+        *counts.entry((sell_price(item.as_ref()), name)).or_insert(0) += 1;
+    }
+    let mut items: Vec<_> = counts
+        .into_iter()
+        .map(|((price, name), count)| (price, name, count))
+        .collect();
+    items.sort();
     items
 }
 
-trait Shoppable: Display {
-    fn cost(&self) -> i32;
+/// Sell the given items from the inventory.
+/// Rare rings are only sold if `confirmed` is true. As with buying, it
+/// will stop at the first item that's not found but keep what was sold so far.
+pub fn sell(game: &mut Game, item_keys: &[Key], confirmed: bool) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Shop is only allowed at home.");
+    }
+
+    // check before selling anything, since it can't be undone
+    if let Some(key) = item_keys.iter().find(|key| is_rare(key)) {
+        if !confirmed {
+            bail!("{} is a rare ring, pass --yes to confirm selling it.", key);
+        }
+    }
+
+    let mut item_counts = HashMap::new();
+    let mut total_price = 0;
+    let mut error = String::from("");
+
+    for key in item_keys {
+        if let Some(item) = game.take_item(key) {
+            let price = sell_price(item.as_ref());
+            game.gold += price;
+            total_price += price;
+            *item_counts.entry(key.clone()).or_insert(0) += 1;
+
+            // sold rings can be found again in chests, except the
+            // ones that can only be bought
+            if let Key::Ring(ring) = key {
+                if *ring != Ring::Diamond {
+                    game.ring_pool.insert(ring.clone());
+                }
+            }
+            quest::item_sold(game, key.clone());
+        } else {
+            error = format!("{} not found.", key);
+            break;
+        }
+    }
+
+    log::shop_sell(total_price, &item_counts);
+    if total_price > 0 {
+        quest::gold_earned(game, total_price);
+    }
+    if !error.is_empty() {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Sell every item in the inventory except the ones of the given types.
+/// Rare rings are kept unless `confirmed` is true.
+pub fn sell_all_except(game: &mut Game, keep: &[Key], confirmed: bool) -> Result<()> {
+    let keys: Vec<Key> = game
+        .inventory
+        .iter()
+        .filter(|(key, _)| !keep.contains(key) && (confirmed || !is_rare(key)))
+        .flat_map(|(key, items)| std::iter::repeat_n(key.clone(), items.len()))
+        .collect();
+    sell(game, &keys, confirmed)
+}
+
+/// The gold paid by the shop for the given item: a fraction of its price
+/// for items sold at the shop and a base value for the rest.
+fn sell_price(item: &dyn Item) -> i32 {
+    let value = match item.key() {
+        Key::Ring(Ring::Diamond) => Ring::Diamond.cost(),
+        Key::Ring(Ring::Ruling) => 10_000,
+        Key::Ring(_) => 2_500,
+        Key::LevelStone => 5_000,
+        Key::Chest => 1_000,
+        Key::HealthStone | Key::MagicStone | Key::PowerStone | Key::SpeedStone => 1_250,
+        _ => item.as_shoppable().map_or(0, |shoppable| shoppable.cost()),
+    };
+    value * SELL_PERCENT / 100
+}
+
+/// Rings that are hard to get back once sold.
+fn is_rare(key: &Key) -> bool {
+    matches!(
+        key,
+        Key::Ring(Ring::Ruling | Ring::Diamond | Ring::Revive | Ring::Double)
+    )
+}
+
+/// Build a list of items currently available at the shop
+fn available_items(player: &Character) -> Vec<Box<dyn Shoppable>> {
+    catalog()
+        .iter()
+        .filter(|entry| player.level >= entry.min_level)
+        .filter_map(|entry| shoppable(&entry.item, player))
+        .collect()
+}
+
+/// Build the item of the given kind at the hero level, if the shop can
+/// offer it to them: weapons only for the classes that wield them, helmets
+/// only for magic classes and equipment only if it's an upgrade of the
+/// worn piece.
+fn shoppable(key: &Key, player: &Character) -> Option<Box<dyn Shoppable>> {
+    let level = player.rounded_level();
+    let item: Box<dyn Shoppable> = match key {
+        key if key.is_equipment() => {
+            let (piece, current) = match key {
+                Key::Shield => (Equipment::shield(level), &player.shield),
+                Key::Helmet => (Equipment::helmet(level), &player.helmet),
+                Key::Armor => (Equipment::armor(level), &player.armor),
+                Key::Boots => (Equipment::boots(level), &player.boots),
+                Key::Amulet => (Equipment::amulet(level), &player.amulet),
+                _ => (Equipment::class_weapon(&player.class, level), &player.sword),
+            };
+            if piece.key() != *key || !player.can_use(key) || !piece.is_upgrade_from(current) {
+                return None;
+            }
+            Box::new(piece)
+        }
+        Key::Potion => Box::new(super::Potion::new(level)),
+        Key::Ether => Box::new(super::Ether::new(level)),
+        Key::Bomb => Box::new(super::Bomb::new(level)),
+        Key::Remedy => Box::new(super::Remedy::new()),
+        Key::Escape => Box::new(super::Escape::new()),
+        Key::Antidote => Box::new(super::Antidote::new()),
+        Key::BurnHeal => Box::new(super::BurnHeal::new()),
+        Key::Identify => Box::new(super::Identify::new()),
+        Key::HolyWater => Box::new(super::HolyWater::new()),
+        Key::SmokeBomb => Box::new(super::SmokeBomb::new()),
+        Key::Warp => Box::new(super::Warp::new()),
+        Key::Elixir => Box::new(super::Elixir::new()),
+        Key::Ring(ring) => Box::new(ring.clone()),
+        _ => return None,
+    };
+    Some(item)
+}
+
+/// The price of a regular item at the shop, after the hero discounts.
+fn shop_cost(game: &Game, item: &dyn Shoppable) -> i32 {
+    let discount = entry(&item.to_key()).map_or(0, |entry| entry.discount(game));
+    item.cost() * (100 - discount) / 100
+}
+
+pub trait Shoppable: Display {
     fn add_to(&self, game: &mut Game);
     fn to_key(&self) -> Key;
+
+    /// The level the catalog price is scaled by.
+    fn price_level(&self) -> i32 {
+        1
+    }
+
+    /// The price before discounts, as defined by the catalog. Items missing
+    /// from a custom catalog, e.g. sold by the merchant, use the built-in one.
+    fn cost(&self) -> i32 {
+        let key = self.to_key();
+        entry(&key)
+            .or_else(|| DEFAULT_CATALOG.iter().find(|entry| entry.item == key))
+            .map_or(0, |entry| entry.price(self.price_level()))
+    }
 }
 
 impl Shoppable for Equipment {
-    fn cost(&self) -> i32 {
-        self.level() * 500
+    fn price_level(&self) -> i32 {
+        self.level()
     }
 
     fn add_to(&self, game: &mut Game) {
-        match self.key() {
-            Key::Sword => game.player.sword = Some(self.clone()),
-            Key::Shield => game.player.shield = Some(self.clone()),
-            _ => {}
+        if let Some(previous) = game.player.equip(self.clone()) {
+            game.add_or_leave(Box::new(previous));
         }
     }
 
@@ -131,12 +614,12 @@ impl Shoppable for Equipment {
 }
 
 impl Shoppable for super::Potion {
-    fn cost(&self) -> i32 {
-        self.level * 200
+    fn price_level(&self) -> i32 {
+        self.level
     }
 
     fn add_to(&self, game: &mut Game) {
-        game.add_item(Box::new(self.clone()));
+        game.add_or_leave(Box::new(self.clone()));
     }
 
     fn to_key(&self) -> Key {
@@ -145,12 +628,18 @@ impl Shoppable for super::Potion {
 }
 
 impl Shoppable for super::Escape {
-    fn cost(&self) -> i32 {
-        1000
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
     }
 
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for super::Remedy {
     fn add_to(&self, game: &mut Game) {
-        game.add_item(Box::new(self.clone()));
+        game.add_or_leave(Box::new(self.clone()));
     }
 
     fn to_key(&self) -> Key {
@@ -158,13 +647,13 @@ impl Shoppable for super::Escape {
     }
 }
 
-impl Shoppable for super::Remedy {
-    fn cost(&self) -> i32 {
-        400
+impl Shoppable for super::Ether {
+    fn price_level(&self) -> i32 {
+        self.level
     }
 
     fn add_to(&self, game: &mut Game) {
-        game.add_item(Box::new(self.clone()));
+        game.add_or_leave(Box::new(self.clone()));
     }
 
     fn to_key(&self) -> Key {
@@ -172,13 +661,23 @@ impl Shoppable for super::Remedy {
     }
 }
 
-impl Shoppable for super::Ether {
-    fn cost(&self) -> i32 {
-        self.level * 250
+impl Shoppable for super::Elixir {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for super::Bomb {
+    fn price_level(&self) -> i32 {
+        self.level
     }
 
     fn add_to(&self, game: &mut Game) {
-        game.add_item(Box::new(self.clone()));
+        game.add_or_leave(Box::new(self.clone()));
     }
 
     fn to_key(&self) -> Key {
@@ -186,13 +685,69 @@ impl Shoppable for super::Ether {
     }
 }
 
-impl Shoppable for Ring {
-    fn cost(&self) -> i32 {
-        50_000
+impl Shoppable for super::SmokeBomb {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
     }
+}
 
+impl Shoppable for super::Antidote {
     fn add_to(&self, game: &mut Game) {
-        game.add_item(Box::new(self.clone()));
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for super::BurnHeal {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for super::Warp {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for super::Identify {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for super::HolyWater {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
+impl Shoppable for Ring {
+    fn add_to(&self, game: &mut Game) {
+        game.add_or_leave(Box::new(self.clone()));
     }
 
     fn to_key(&self) -> Key {
@@ -204,6 +759,7 @@ impl Shoppable for Ring {
 mod tests {
     use super::super::Potion;
     use super::*;
+    use crate::character::class::Class;
 
     #[test]
     fn buy_one() {
@@ -241,6 +797,68 @@ mod tests {
         assert_eq!(2, *game.inventory().get(&Key::Potion).unwrap());
     }
 
+    #[test]
+    fn sell_items() {
+        let mut game = Game::new();
+        game.add_item(Box::new(super::super::Potion::new(1)));
+        game.add_item(Box::new(super::super::Potion::new(1)));
+        game.add_item(Box::new(Equipment::sword(1)));
+        game.ring_pool.remove(&Ring::Revive);
+        game.add_item(Box::new(Ring::Revive));
+
+        assert!(sell(&mut game, &[Key::Potion], false).is_ok());
+        assert_eq!(80, game.gold);
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+
+        // not found
+        assert!(sell(&mut game, &[Key::Shield], false).is_err());
+        assert_eq!(80, game.gold);
+
+        // rare rings require confirmation
+        assert!(sell(&mut game, &[Key::Sword, Key::Ring(Ring::Revive)], false).is_err());
+        assert_eq!(80, game.gold);
+        assert!(game.inventory().contains_key(&Key::Sword));
+
+        // keep potions and the ring
+        assert!(sell_all_except(&mut game, &[Key::Potion], false).is_ok());
+        assert_eq!(280, game.gold);
+        assert!(!game.inventory().contains_key(&Key::Sword));
+        assert!(game.inventory().contains_key(&Key::Ring(Ring::Revive)));
+
+        assert!(sell(&mut game, &[Key::Ring(Ring::Revive)], true).is_ok());
+        assert_eq!(1280, game.gold);
+        assert!(game.ring_pool.contains(&Ring::Revive));
+        assert_eq!(1, game.inventory().len());
+    }
+
+    #[test]
+    fn sell_mixed_levels() {
+        let mut game = Game::new();
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(Potion::new(10)));
+        game.add_item(Box::new(Potion::new(10)));
+
+        let rows = sell_rows(&game);
+        assert_eq!(2, rows.len());
+        assert_eq!((80, String::from("potion[1]"), 1), rows[0]);
+        assert_eq!((800, String::from("potion[10]"), 2), rows[1]);
+    }
+
+    #[test]
+    fn repair_equipment() {
+        let mut game = Game::new();
+        game.player.sword = Some(Equipment::sword(10).with_durability(1));
+        game.player.shield = Some(Equipment::shield(10));
+
+        let cost = game.player.sword.as_ref().unwrap().repair_cost();
+        assert!(repair(&mut game).is_err());
+
+        game.gold = cost;
+        assert!(repair(&mut game).is_ok());
+        assert_eq!(0, game.gold);
+        assert_eq!(0, game.player.sword.as_ref().unwrap().repair_cost());
+    }
+
     #[test]
     fn buy_until_not_available() {
         let mut game = Game::new();
@@ -265,4 +883,105 @@ mod tests {
         assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
         assert!(game.player.shield.is_some());
     }
+
+    #[test]
+    fn stock() {
+        let mut game = Game::new();
+        game.gold = 10_000;
+
+        let potions = vec![Key::Potion; ITEM_STOCK as usize + 1];
+        assert!(buy(&mut game, &potions).is_err());
+        assert_eq!(
+            ITEM_STOCK as usize,
+            *game.inventory().get(&Key::Potion).unwrap()
+        );
+
+        // restocked after enough battles
+        for _ in 0..RESTOCK_BATTLES {
+            game.shop.battle_won();
+        }
+        assert!(buy(&mut game, &[Key::Potion]).is_ok());
+
+        // the stock survives death
+        game.reset();
+        game.gold = 10_000;
+        let potions = vec![Key::Potion; ITEM_STOCK as usize];
+        assert!(buy(&mut game, &potions).is_err());
+    }
+
+    #[test]
+    fn buy_special() {
+        let mut game = Game::new();
+        game.gold = 10_000;
+        restock(&mut game);
+        assert!(game.shop.specials.is_empty());
+        assert!(buy(&mut game, &[Key::PowerStone]).is_err());
+
+        game.shop.specials.push(Special {
+            item: Box::new(stone::Power),
+            cost: 3_750,
+        });
+        game.ring_pool.remove(&Ring::Speed);
+        game.shop.specials.push(Special {
+            item: Box::new(Ring::Speed),
+            cost: 22_500,
+        });
+        assert!(buy(&mut game, &[Key::PowerStone, Key::PowerStone]).is_err());
+        assert_eq!(6_250, game.gold);
+        assert!(game.inventory().contains_key(&Key::PowerStone));
+
+        // rings found meanwhile are no longer offered
+        game.gold = 22_500;
+        assert!(buy(&mut game, &[Key::Ring(Ring::Speed)]).is_err());
+        assert!(game.shop.specials.is_empty());
+        assert_eq!(22_500, game.gold);
+    }
+
+    #[test]
+    fn catalog() {
+        // level gating and class weapons in the built-in catalog
+        let mut game = Game::new();
+        let keys = |game: &Game| -> Vec<Key> {
+            available_items(&game.player)
+                .iter()
+                .map(|item| item.to_key())
+                .collect()
+        };
+        assert!(keys(&game).contains(&Key::Axe));
+        assert!(!keys(&game).contains(&Key::Dagger));
+        assert!(!keys(&game).contains(&Key::Bomb));
+        // helmets are only offered to magic classes
+        assert!(!keys(&game).contains(&Key::Helmet));
+        game.player.class = Class::player_by_name("mage").unwrap().clone();
+        assert!(keys(&game).contains(&Key::Helmet));
+        game.player.class = Class::player_by_name("thief").unwrap().clone();
+        game.player.level = 5;
+        assert!(keys(&game).contains(&Key::Dagger));
+        assert!(!keys(&game).contains(&Key::Axe));
+        assert!(keys(&game).contains(&Key::Bomb));
+
+        let entries: Vec<Entry> = serde_yaml::from_slice(
+            b"
+- item: potion
+  price: {base: 100, per_level: 50}
+  stock: 2
+  discounts:
+    - class: thief
+      percent: 10
+    - class: warrior
+      quest: buy a weapon
+      percent: 20
+",
+        )
+        .unwrap();
+        let potion = &entries[0];
+        assert_eq!(Some(2), potion.stock);
+        assert_eq!(350, potion.price(5));
+
+        assert_eq!(10, potion.discount(&game));
+        game.player.class = Class::player_by_name("warrior").unwrap().clone();
+        assert_eq!(0, potion.discount(&game));
+        quest::item_bought(&mut game, Key::Sword);
+        assert_eq!(20, potion.discount(&game));
+    }
 }
@@ -0,0 +1,210 @@
+use super::key::Key;
+use super::Item;
+use crate::game::Game;
+use crate::log;
+use crate::quest;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Slots available in a new stash.
+const INITIAL_SLOTS: i32 = 10;
+
+/// Slots added on each expansion.
+const EXPANSION_SLOTS: i32 = 5;
+
+/// Gold paid per current slot to expand the stash.
+const EXPANSION_COST_PER_SLOT: i32 = 250;
+
+/// Storage kept at home, outside of the hero, so its contents survive death.
+/// Each item takes one slot.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stash {
+    items: HashMap<Key, Vec<Box<dyn Item>>>,
+    slots: i32,
+}
+
+impl Default for Stash {
+    fn default() -> Self {
+        Self {
+            items: HashMap::new(),
+            slots: INITIAL_SLOTS,
+        }
+    }
+}
+
+impl Stash {
+    pub fn len(&self) -> i32 {
+        self.items.values().map(|items| items.len() as i32).sum()
+    }
+
+    pub fn slots(&self) -> i32 {
+        self.slots
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.items.contains_key(key)
+    }
+
+    /// Return the amount of each item type in the stash.
+    pub fn counts(&self) -> HashMap<&Key, usize> {
+        self.items
+            .iter()
+            .map(|(k, v)| (k, v.len()))
+            .collect::<HashMap<&Key, usize>>()
+    }
+
+    /// Gold required to add more slots to the stash.
+    pub fn expansion_cost(&self) -> i32 {
+        self.slots * EXPANSION_COST_PER_SLOT
+    }
+
+    fn add(&mut self, item: Box<dyn Item>) {
+        self.items.entry(item.key()).or_default().push(item);
+    }
+
+    fn take(&mut self, key: &Key) -> Option<Box<dyn Item>> {
+        let items = self.items.get_mut(key)?;
+        let item = items.pop();
+        if items.is_empty() {
+            self.items.remove(key);
+        }
+        item
+    }
+}
+
+/// Move the given items from the inventory to the stash.
+pub fn put(game: &mut Game, keys: &[Key]) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Stash is only available at home.");
+    }
+
+    let mut item_counts = HashMap::new();
+    let mut error = None;
+    for key in keys {
+        if game.stash.len() >= game.stash.slots() {
+            error = Some("Stash is full.".to_string());
+            break;
+        }
+        if let Some(item) = game.take_item(key) {
+            game.stash.add(item);
+            *item_counts.entry(key.clone()).or_insert(0) += 1;
+        } else {
+            error = Some(format!("{} not found in the inventory.", key));
+            break;
+        }
+    }
+
+    log::stash_put(&item_counts);
+    if let Some(error) = error {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Move the given items from the stash back to the inventory.
+pub fn take(game: &mut Game, keys: &[Key]) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Stash is only available at home.");
+    }
+
+    let mut item_counts = HashMap::new();
+    let mut error = None;
+    for key in keys {
+        let Some(item) = game.stash.take(key) else {
+            error = Some(format!("{} not found in the stash.", key));
+            break;
+        };
+        if !game.has_room(item.weight()) {
+            game.stash.add(item);
+            error = Some("Inventory is full.".to_string());
+            break;
+        }
+        game.add_item(item);
+        *item_counts.entry(key.clone()).or_insert(0) += 1;
+    }
+
+    log::stash_take(&item_counts);
+    if let Some(error) = error {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Print the contents of the stash.
+pub fn list(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Stash is only available at home.");
+    }
+
+    log::stash_list(&game.stash);
+    Ok(())
+}
+
+/// Pay gold to add more slots to the stash.
+pub fn expand(game: &mut Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Stash is only available at home.");
+    }
+
+    let cost = game.stash.expansion_cost();
+    if game.gold < cost {
+        bail!("Not enough gold.");
+    }
+    game.gold -= cost;
+    game.stash.slots += EXPANSION_SLOTS;
+    log::stash_expand(cost, game.stash.slots);
+    quest::gold_spent(game, cost);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::equipment::Equipment;
+    use crate::item::Potion;
+
+    #[test]
+    fn put_take() {
+        let mut game = Game::new();
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(Equipment::shield(3)));
+
+        assert!(put(&mut game, &[Key::Potion, Key::Shield]).is_ok());
+        assert_eq!(2, game.stash.len());
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+        assert!(!game.inventory().contains_key(&Key::Shield));
+
+        // missing items
+        assert!(put(&mut game, &[Key::Escape]).is_err());
+        assert!(take(&mut game, &[Key::Escape]).is_err());
+
+        // the stash survives death
+        game.reset();
+        assert!(game.inventory().is_empty());
+        assert!(take(&mut game, &[Key::Shield]).is_ok());
+        assert!(game.inventory().contains_key(&Key::Shield));
+        assert_eq!(1, game.stash.len());
+    }
+
+    #[test]
+    fn slots() {
+        let mut game = Game::new();
+        for _ in 0..INITIAL_SLOTS {
+            game.stash.add(Box::new(Potion::new(1)));
+        }
+        game.add_item(Box::new(Potion::new(1)));
+        assert!(put(&mut game, &[Key::Potion]).is_err());
+
+        assert!(expand(&mut game).is_err());
+        let cost = game.stash.expansion_cost();
+        game.gold = cost;
+        assert!(expand(&mut game).is_ok());
+        assert_eq!(0, game.gold);
+        assert_eq!(cost, game.stats.gold_spent);
+        assert_eq!(INITIAL_SLOTS + EXPANSION_SLOTS, game.stash.slots());
+        assert!(put(&mut game, &[Key::Potion]).is_ok());
+    }
+}
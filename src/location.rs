@@ -30,6 +30,10 @@ impl Location {
         self.path.to_string_lossy().to_string()
     }
 
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+
     pub fn home() -> Self {
         Self {
             path: dirs::home_dir().unwrap(),
@@ -1,8 +1,16 @@
+use crate::bank::Bank;
 use crate::character::AttackType;
-use crate::character::{Character, StatusEffect};
+use crate::character::{Character, StatusEffect, MAX_FATIGUE};
+use crate::dungeon::{self, Record, Repo};
 use crate::game::Game;
+use crate::item::curse::Curse;
+use crate::item::equipment::{Equipment, Rarity};
 use crate::item::key::Key;
+use crate::item::ring::Ring;
+use crate::item::stash::Stash;
+use crate::item::Item;
 use crate::location::Location;
+use crate::stats::Stats;
 use colored::*;
 use once_cell::sync::OnceCell;
 use std::collections::HashMap;
@@ -52,7 +60,11 @@ pub fn status_effect(character: &Character, hp: i32, mp: i32) {
     }
 }
 
-pub fn battle_won(game: &Game, xp: i32, levels_up: i32, gold: i32, items: &HashMap<Key, i32>) {
+pub fn equipment_broke(character: &Character, equipment: &Equipment) {
+    battle_log(character, &format!("{} broke!", equipment).bright_red());
+}
+
+pub fn battle_won(game: &Game, xp: i32, levels_up: i32, gold: i32, items: &HashMap<String, i32>) {
     battle_log(
         &game.player,
         &format!(
@@ -69,14 +81,63 @@ pub fn battle_lost(player: &Character) {
     battle_log(player, "\u{1F480}");
 }
 
-pub fn chest(items: &HashMap<Key, i32>, gold: i32) {
+pub fn chest(items: &HashMap<String, i32>, gold: i32) {
     println!("{}", format_ls("\u{1F4E6}", items, gold));
 }
 
-pub fn tombstone(items: &HashMap<Key, i32>, gold: i32) {
+pub fn tombstone(items: &HashMap<String, i32>, gold: i32) {
     println!("{}", format_ls("\u{1FAA6} ", items, gold));
 }
 
+pub fn merchant_appears(moves: i32) {
+    println!(
+        "\u{1F9F3} a travelling merchant is here for {} moves, run rpg buy",
+        moves
+    );
+}
+
+pub fn merchant_leaves(moves: i32) {
+    println!("    the merchant leaves in {} moves", moves);
+}
+
+pub fn dungeon_entered(repo: &Repo, record: Option<&Record>) {
+    let cleared = if record.is_some_and(|record| record.cleared) {
+        " (cleared)"
+    } else {
+        ""
+    };
+    println!(
+        "\u{1F3F0} {} dungeon, tier {}{}",
+        repo.name.bold(),
+        repo.tier(),
+        cleared
+    );
+}
+
+pub fn dungeon_cleared(name: &str) {
+    println!("\u{1F3C6} {} dungeon cleared!", name.bold());
+}
+
+pub fn locked_chest() {
+    println!("\u{1F512} locked chest");
+}
+
+pub fn unlocked_chest(opened_with: &str, items: &HashMap<String, i32>, gold: i32) {
+    println!(
+        "{} (opened with {})",
+        format_ls("\u{1F513}", items, gold),
+        opened_with
+    );
+}
+
+pub fn drop_items(key: &Key, count: usize) {
+    println!("  -{}x{} dropped", key, count);
+}
+
+pub fn inventory_full() {
+    println!("  inventory full, some items were left behind");
+}
+
 pub fn bribe(player: &Character, amount: i32) {
     if amount > 0 {
         let suffix = format!("bribed {}", format_gold_signed(-amount));
@@ -133,12 +194,37 @@ pub fn heal(
     }
 }
 
+pub fn camp(
+    player: &Character,
+    location: &Location,
+    recovered_hp: i32,
+    recovered_mp: i32,
+    recovered_fatigue: i32,
+) {
+    let fatigue = format!("-{}fatigue ", recovered_fatigue).cyan();
+    log(
+        player,
+        location,
+        &format_stat_change(
+            player,
+            recovered_hp,
+            recovered_mp,
+            false,
+            &format!("{}\u{26FA}", fatigue),
+        ),
+    );
+}
+
 pub fn change_class(player: &Character, lost_xp: i32) {
     if lost_xp > 0 {
         battle_log(player, &format!("-{}xp", lost_xp).bright_red());
     }
 }
 
+pub fn prestige(player: &Character) {
+    battle_log(player, &"prestige!".cyan().bold().to_string());
+}
+
 pub fn stat_increase(player: &Character, stat: &str, increase: i32) {
     let suffix = if stat == "level" {
         level_up(increase)
@@ -159,17 +245,190 @@ pub fn status(game: &Game) {
     }
 }
 
-pub fn shop_list(game: &Game, items: Vec<(i32, String)>) {
-    for (cost, item) in items {
-        println!("    {:<10}  {}", item, format_gold(cost));
+pub fn shop_list(game: &Game, items: Vec<(i32, String, i32)>) {
+    print_shop_items(items);
+    println!("\n    funds: {}", format_gold(game.gold));
+}
+
+pub fn shop_stock(
+    game: &Game,
+    items: Vec<(i32, String, i32)>,
+    specials: Vec<(i32, String, i32)>,
+    restock: (u64, i32),
+) {
+    print_shop_items(items);
+    if !specials.is_empty() {
+        println!("\n    specials:");
+        print_shop_items(specials);
     }
 
-    println!("\n    funds: {}", format_gold(game.gold));
+    let (seconds, battles) = restock;
+    println!(
+        "\n    restock in {}h or {} battles",
+        seconds.div_ceil(3600),
+        battles
+    );
+    println!("    funds: {}", format_gold(game.gold));
+}
+
+fn print_shop_items(items: Vec<(i32, String, i32)>) {
+    for (cost, item, count) in items {
+        println!("    {:<10}  {:<8} x{}", item, format_gold(cost), count);
+    }
 }
 
 pub fn shop_buy(cost: i32, items: &HashMap<Key, i32>) {
     if !items.is_empty() {
-        println!("  {}", format_ls("", items, -cost));
+        let items = items.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        println!("  {}", format_ls("", &items, -cost));
+    }
+}
+
+pub fn shop_sell(price: i32, items: &HashMap<Key, i32>) {
+    if !items.is_empty() {
+        let mut string = format!("  {} ", format_gold_signed(price));
+        for (key, count) in items {
+            string.push_str(&format!("-{}x{} ", key, count));
+        }
+        println!("{}", string);
+    }
+}
+
+pub fn stash_put(items: &HashMap<Key, i32>) {
+    if !items.is_empty() {
+        println!("  stashed {}", format_item_counts(items, "-"));
+    }
+}
+
+pub fn stash_take(items: &HashMap<Key, i32>) {
+    if !items.is_empty() {
+        println!("  took {}", format_item_counts(items, "+"));
+    }
+}
+
+pub fn stash_list(stash: &Stash) {
+    let mut items = stash
+        .counts()
+        .iter()
+        .map(|(k, v)| format!("{}x{}", k, v))
+        .collect::<Vec<String>>();
+    items.sort();
+    println!(
+        "  stash[{}/{}]:{{{}}}",
+        stash.len(),
+        stash.slots(),
+        items.join(",")
+    );
+    println!(
+        "  {} to expand",
+        format_gold(stash.expansion_cost()).dimmed()
+    );
+}
+
+pub fn stash_expand(cost: i32, slots: i32) {
+    println!(
+        "  {} stash expanded to {} slots",
+        format_gold_signed(-cost),
+        slots
+    );
+}
+
+pub fn bank_deposit(amount: i32, fee: i32) {
+    println!(
+        "  {} deposited {}",
+        format_gold_signed(-amount),
+        format!("({}g fee)", fee).dimmed()
+    );
+}
+
+pub fn bank_withdraw(amount: i32) {
+    println!("  {} withdrawn", format_gold_signed(amount));
+}
+
+pub fn bank_interest(interest: i32, balance: i32) {
+    println!(
+        "    {} bank interest {}",
+        format_gold_signed(interest),
+        format!("({}g balance)", balance).dimmed()
+    );
+}
+
+pub fn bank_status(bank: &Bank) {
+    println!("  bank:{}", format_gold(bank.balance));
+    println!("  death penalty: {}", bank.penalty);
+}
+
+pub fn repair(cost: i32) {
+    println!("  {} repaired", format_gold_signed(-cost));
+}
+
+pub fn cursed(item: &str, curse: Curse) {
+    println!("  {} is cursed! {}", item, curse.describe().red());
+}
+
+pub fn identified(items: &[(String, Option<Curse>)], cost: i32) {
+    let mut string = String::from(" ");
+    if cost > 0 {
+        string.push_str(&format!(" {}", format_gold_signed(-cost)));
+    }
+    for (item, curse) in items {
+        match curse {
+            Some(curse) => string.push_str(&format!(" {}({})", item, curse.to_string().red())),
+            None => string.push_str(&format!(" {}", item)),
+        }
+    }
+    if items.is_empty() {
+        string.push_str(" nothing to identify");
+    }
+    println!("{}", string);
+}
+
+pub fn purified() {
+    println!("  curses removed");
+}
+
+pub fn ring_upgraded(player: &Character, ring: &Ring) {
+    println!("  {} upgraded", format_ring(player, ring));
+}
+
+pub fn compare(item: &str, other: &str, stats: &[(&str, i32, i32)]) {
+    println!("  {} vs {}", item, other.dimmed());
+    for (name, before, after) in stats {
+        let delta = after - before;
+        let delta = match delta {
+            0 => String::from("=").dimmed(),
+            d if d > 0 => format!("+{}", d).green(),
+            d => d.to_string().red(),
+        };
+        println!("    {}:{} -> {} {}", name, before, after, delta);
+    }
+}
+
+pub fn loadout_saved(name: &str) {
+    println!("  loadout {} saved", name);
+}
+
+pub fn loadout_used(name: &str) {
+    println!("  loadout {} equipped", name);
+}
+
+pub fn loadout_list(loadouts: Vec<(String, String)>) {
+    for (name, items) in loadouts {
+        println!("  {}:{{{}}}", name, items);
+    }
+}
+
+pub fn craft(item: &str) {
+    println!("  +{} crafted", item);
+}
+
+pub fn recipe_list(recipes: Vec<(bool, String)>) {
+    for (available, recipe) in recipes {
+        if available {
+            println!("  {}", recipe);
+        } else {
+            println!("  {}", recipe.dimmed());
+        }
     }
 }
 
@@ -183,6 +442,62 @@ pub fn quest_list(quests: Vec<(bool, String)>) {
     }
 }
 
+pub fn lifetime_stats(stats: &Stats) {
+    let format_counts = |counts: &HashMap<String, i32>| {
+        let mut entries = counts
+            .iter()
+            .map(|(k, v)| format!("{}x{}", k, v))
+            .collect::<Vec<String>>();
+        entries.sort();
+        format!("{{{}}}", entries.join(","))
+    };
+    let total = |counts: &HashMap<String, i32>| counts.values().sum::<i32>();
+
+    println!(
+        "    battles won: {} {}",
+        total(&stats.battles_won),
+        format_counts(&stats.battles_won)
+    );
+    println!(
+        "    battles lost: {} {}",
+        total(&stats.battles_lost),
+        format_counts(&stats.battles_lost)
+    );
+    println!(
+        "    deaths: {} {}",
+        total(&stats.deaths),
+        format_counts(&stats.deaths)
+    );
+    println!(
+        "    gold earned: {}   spent: {}",
+        format_gold(stats.gold_earned),
+        format_gold(stats.gold_spent)
+    );
+    println!("    deepest distance: {}", stats.deepest_distance);
+    println!(
+        "    chests found: {}   rings found: {}",
+        stats.chests_found,
+        stats.rings_found.len()
+    );
+    let items_used = stats
+        .items_used
+        .iter()
+        .map(|(k, v)| (k.to_string(), *v))
+        .collect();
+    println!(
+        "    items used: {} {}",
+        total(&items_used),
+        format_counts(&items_used)
+    );
+    println!("    bribes: {}   runs: {}", stats.bribes, stats.runs);
+}
+
+pub fn achievement_unlocked(description: &str) {
+    if !quiet() {
+        println!("   \u{1F3C6} achievement unlocked: {}", description.bold());
+    }
+}
+
 pub fn quest_done(reward: i32) {
     if !quiet() {
         println!("   {} quest completed!", format_gold_signed(reward));
@@ -203,6 +518,9 @@ fn long_status(game: &Game) {
     let location = &game.location;
 
     println!("{}@{}", format_character(player), location);
+    if let Some(repo) = dungeon::current(game) {
+        println!("    dungeon: {} tier {}", repo.name, repo.tier());
+    }
     println!(
         "    hp:{} {}/{}",
         hp_display(player, 10),
@@ -231,6 +549,9 @@ fn long_status(game: &Game) {
     if let Some(status) = player.status_effect {
         println!("    status: {}", format_status_effect(status).bright_red());
     }
+    if player.fatigue > 0 {
+        println!("    fatigue: {}/{}", player.fatigue, MAX_FATIGUE);
+    }
     println!(
         "    att:{}   mag:{}   def:{}   spd:{}",
         player.physical_attack(),
@@ -287,7 +608,7 @@ fn plain_status(game: &Game) {
     );
 }
 
-fn format_ls(emoji: &str, items: &HashMap<Key, i32>, gold: i32) -> String {
+fn format_ls(emoji: &str, items: &HashMap<String, i32>, gold: i32) -> String {
     let mut string = format!("{} ", emoji);
 
     if gold != 0 {
@@ -301,6 +622,15 @@ fn format_ls(emoji: &str, items: &HashMap<Key, i32>, gold: i32) -> String {
 
 // HELPERS
 
+fn format_item_counts(items: &HashMap<Key, i32>, sign: &str) -> String {
+    let mut items = items
+        .iter()
+        .map(|(key, count)| format!("{}{}x{}", sign, key, count))
+        .collect::<Vec<String>>();
+    items.sort();
+    items.join(" ")
+}
+
 /// Generic log function. At the moment all output of the game is structured as
 /// of a player status at some location, with an optional event suffix.
 fn log(character: &Character, location: &Location, suffix: &str) {
@@ -333,40 +663,89 @@ fn format_character(character: &Character) -> String {
     } else {
         name.yellow().bold()
     };
-    format!("{}[{}]", name, character.level)
+    let prestige = if character.prestige > 0 {
+        format!("\u{2605}{}", character.prestige).cyan().to_string()
+    } else {
+        String::new()
+    };
+    format!("{}{}[{}]", name, prestige, character.level)
 }
 
 fn format_equipment(character: &Character) -> String {
     let mut fragments = Vec::new();
 
-    if let Some(sword) = &character.sword {
-        fragments.push(sword.to_string());
+    for equipment in character.equipment() {
+        fragments.push(format_equipment_piece(equipment));
     }
 
-    if let Some(shield) = &character.shield {
-        fragments.push(shield.to_string());
+    for ring in [&character.left_ring, &character.right_ring]
+        .into_iter()
+        .flatten()
+    {
+        fragments.push(format_ring(character, ring));
     }
 
-    if let Some(ring) = &character.left_ring {
-        fragments.push(ring.to_string());
+    for set in character.ring_sets() {
+        fragments.push(set.to_string().bold().to_string());
+    }
+
+    format!("equip:{{{}}}", fragments.join(","))
+}
+
+/// Show the ring level when it was upgraded.
+fn format_ring(character: &Character, ring: &Ring) -> String {
+    let level = character.ring_level(ring);
+    if level > 1 {
+        format!("{}[{}]", ring, level)
+    } else {
+        ring.to_string()
     }
+}
 
-    if let Some(ring) = &character.right_ring {
-        fragments.push(ring.to_string());
+/// Color the equipment according to its rarity.
+fn format_equipment_piece(equipment: &Equipment) -> String {
+    color_by_rarity(equipment.to_string(), equipment.rarity())
+}
+
+/// The name of an item as shown in chests, loot and the inventory, with
+/// identified equipment colored according to its rarity.
+pub fn format_item_name(game: &Game, item: &dyn Item) -> String {
+    let name = game.item_name(item);
+    match item.as_equipment() {
+        Some(equipment) if game.is_identified(item) => color_by_rarity(name, equipment.rarity()),
+        _ => name,
     }
+}
 
-    format!("equip:{{{}}}", fragments.join(","))
+fn color_by_rarity(display: String, rarity: Rarity) -> String {
+    match rarity {
+        Rarity::Common => display,
+        Rarity::Magic => display.blue().to_string(),
+        Rarity::Rare => display.yellow().to_string(),
+        Rarity::Unique => display.purple().to_string(),
+    }
 }
 
 pub fn format_inventory(game: &Game) -> String {
-    let mut items = game
-        .inventory()
+    // unidentified items are counted separately under their hidden name
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for item in game.inventory.values().flatten() {
+        *counts
+            .entry(format_item_name(game, item.as_ref()))
+            .or_default() += 1;
+    }
+    let mut items = counts
         .iter()
         .map(|(k, v)| format!("{}x{}", k, v))
         .collect::<Vec<String>>();
 
     items.sort();
-    format!("item:{{{}}}", items.join(","))
+    format!(
+        "item[{}/{}]:{{{}}}",
+        game.inventory_weight(),
+        game.player.carry_capacity(),
+        items.join(",")
+    )
 }
 
 fn format_attack(receiver: &Character, attack: &AttackType, damage: i32, mp_cost: i32) -> String {
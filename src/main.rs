@@ -1,14 +1,19 @@
 use game::Game;
 
+mod achievement;
+mod bank;
 mod character;
 mod command;
 mod datafile;
+mod dungeon;
 mod game;
 mod item;
 mod location;
 mod log;
 mod quest;
 mod randomizer;
+mod scan;
+mod stats;
 
 use anyhow::Result;
 use clap::{crate_version, Parser};
@@ -46,6 +51,9 @@ fn run_game() -> Result<()> {
     let opts: Opts = Opts::parse();
     log::init(opts.quiet, opts.plain);
     datafile::load_classes();
+    datafile::load_recipes();
+    datafile::load_lairs();
+    datafile::load_shop();
 
     // reset --hard is a special case, it needs to work when we
     // fail to deserialize the game data -- e.g. on backward
@@ -56,12 +64,17 @@ fn run_game() -> Result<()> {
 
     let mut game = datafile::load()?.unwrap_or_else(Game::new);
 
+    // achievements are kept in a separate file, so they are shared
+    // across games and survive hard resets
+    game.achievements = datafile::load_achievements();
+
     let result = command::run(opts.cmd, &mut game);
 
     // save the file regardless of the success of the command.
     // E.g. if the player dies it's an error / exit code 1
     // and that needs to be reflected in the game state.
     datafile::save(&game).unwrap();
+    datafile::save_achievements(&game.achievements).unwrap();
 
     result
 }
@@ -0,0 +1,51 @@
+use super::{Event, Quest};
+use crate::dungeon;
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Clear the dungeons of all the git repositories found directly under
+/// a directory, e.g. `~/dev`, when the quest was assigned.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClearRepos {
+    dir: String,
+    total: usize,
+    to_clear: HashSet<String>,
+}
+
+impl ClearRepos {
+    /// Return None if there are no repositories under the directory,
+    /// since the quest couldn't be completed. The given dungeons are
+    /// counted as already cleared.
+    pub fn new(dir: &Path, cleared: &HashSet<String>) -> Option<Self> {
+        let repos = dungeon::repos_under(dir);
+        if repos.is_empty() {
+            return None;
+        }
+        let dir = dir.to_string_lossy().to_string();
+        Some(Self {
+            dir: Location::from(&dir).map_or(dir, |location| location.to_string()),
+            total: repos.len(),
+            to_clear: repos.difference(cleared).cloned().collect(),
+        })
+    }
+}
+
+#[typetag::serde]
+impl Quest for ClearRepos {
+    fn description(&self) -> String {
+        let cleared = self.total - self.to_clear.len();
+        format!(
+            "clear every repo under {} {}/{}",
+            self.dir, cleared, self.total
+        )
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::DungeonCleared { root } = event {
+            self.to_clear.remove(root);
+        }
+        self.to_clear.is_empty()
+    }
+}
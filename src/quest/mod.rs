@@ -1,13 +1,16 @@
 use crate::character::class;
 use crate::character::Character;
+use crate::dungeon::Repo;
 use crate::game;
 use crate::item::key::Key;
 use crate::location::Location;
 use crate::log;
 use core::fmt;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 mod beat_enemy;
+mod dungeon;
 mod level;
 mod ring;
 mod tutorial;
@@ -35,6 +38,11 @@ impl fmt::Display for dyn Quest {
 #[derive(Serialize, Deserialize, Default)]
 pub struct QuestList {
     quests: Vec<(Status, i32, Box<dyn Quest>)>,
+
+    /// Whether the clear every repo quest was offered, which happens
+    /// when the hero first enters a dungeon.
+    #[serde(default)]
+    repos_offered: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -65,6 +73,52 @@ pub fn battle_won(game: &mut game::Game, enemy: &Character, levels_up: i32) {
     }
 }
 
+pub fn battle_lost(game: &mut game::Game, enemy: &Character) {
+    handle(game, Event::BattleLost { enemy });
+}
+
+pub fn died(game: &mut game::Game, cause: &str) {
+    handle(
+        game,
+        Event::Died {
+            cause: cause.to_string(),
+        },
+    );
+}
+
+pub fn bribed(game: &mut game::Game, amount: i32) {
+    handle(game, Event::Bribed { amount });
+}
+
+pub fn ran_away(game: &mut game::Game) {
+    handle(game, Event::RanAway);
+}
+
+pub fn gold_earned(game: &mut game::Game, amount: i32) {
+    handle(game, Event::GoldEarned { amount });
+}
+
+pub fn gold_spent(game: &mut game::Game, amount: i32) {
+    handle(game, Event::GoldSpent { amount });
+}
+
+pub fn location_visited(game: &mut game::Game) {
+    handle(
+        game,
+        Event::LocationVisited {
+            location: game.location.clone(),
+        },
+    );
+}
+
+pub fn flawless_victory(game: &mut game::Game) {
+    handle(game, Event::FlawlessVictory);
+}
+
+pub fn prestige(game: &mut game::Game, tier: i32) {
+    handle(game, Event::Prestige { tier });
+}
+
 pub fn level_up(game: &mut game::Game, count: i32) {
     handle(
         game,
@@ -80,6 +134,10 @@ pub fn item_bought(game: &mut game::Game, item: Key) {
     handle(game, Event::ItemBought { item });
 }
 
+pub fn item_sold(game: &mut game::Game, item: Key) {
+    handle(game, Event::ItemSold { item });
+}
+
 pub fn item_used(game: &mut game::Game, item: Key) {
     handle(game, Event::ItemUsed { item });
 }
@@ -96,14 +154,58 @@ pub fn tombstone(game: &mut game::Game) {
     handle(game, Event::TombtsoneFound);
 }
 
+/// Offer the quest of clearing every repository next to the given one,
+/// the first time the hero enters a dungeon.
+pub fn dungeon_entered(game: &mut game::Game, repo: &Repo) {
+    if game.quests.repos_offered {
+        return;
+    }
+    let Some(dir) = Path::new(&repo.root).parent() else {
+        return;
+    };
+
+    let cleared = game
+        .dungeons
+        .iter()
+        .filter(|(_, record)| record.cleared)
+        .map(|(root, _)| root.clone())
+        .collect();
+    if let Some(quest) = dungeon::ClearRepos::new(dir, &cleared) {
+        let status = if game.player.level >= 5 {
+            Status::Unlocked
+        } else {
+            Status::Locked(5)
+        };
+        let reward = 5000 * (game.player.prestige + 1);
+        game.quests.quests.push((status, reward, Box::new(quest)));
+        game.quests.repos_offered = true;
+    }
+}
+
+pub fn dungeon_cleared(game: &mut game::Game, root: &str) {
+    handle(
+        game,
+        Event::DungeonCleared {
+            root: root.to_string(),
+        },
+    );
+}
+
 pub fn game_reset(game: &mut game::Game) {
     handle(game, Event::GameReset);
 }
 
 fn handle(game: &mut game::Game, event: Event) {
+    game.stats.handle(&event);
+    game.achievements.handle(&event, &game.player, game.gold);
+
     // it would be preferable to have quests decoupled from the game struct
     // but that makes event handling much more complicated
-    game.gold += game.quests.handle(&event);
+    let reward = game.quests.handle(&event);
+    if reward > 0 {
+        game.gold += reward;
+        game.stats.handle(&Event::GoldEarned { amount: reward });
+    }
 }
 
 pub enum Event<'a> {
@@ -111,6 +213,29 @@ pub enum Event<'a> {
         enemy: &'a Character,
         location: Location,
     },
+    BattleLost {
+        enemy: &'a Character,
+    },
+    Died {
+        cause: String,
+    },
+    Bribed {
+        amount: i32,
+    },
+    RanAway,
+    GoldEarned {
+        amount: i32,
+    },
+    GoldSpent {
+        amount: i32,
+    },
+    LocationVisited {
+        location: Location,
+    },
+    FlawlessVictory,
+    Prestige {
+        tier: i32,
+    },
     LevelUp {
         count: i32,
         current: i32,
@@ -119,6 +244,9 @@ pub enum Event<'a> {
     ItemBought {
         item: Key,
     },
+    ItemSold {
+        item: Key,
+    },
     ItemUsed {
         item: Key,
     },
@@ -127,19 +255,28 @@ pub enum Event<'a> {
     },
     ChestFound,
     TombtsoneFound,
+    DungeonCleared {
+        root: String,
+    },
     GameReset,
 }
 
 impl QuestList {
     pub fn new() -> Self {
-        let mut quests = Self { quests: Vec::new() };
+        Self::with_prestige(0)
+    }
+
+    /// Build the quest list for a hero that already prestiged the given
+    /// amount of times. Higher tiers get harder goals and bigger rewards.
+    pub fn with_prestige(tier: i32) -> Self {
+        let mut quests = Self::default();
 
-        quests.setup();
+        quests.setup(tier);
         quests
     }
 
     /// Load the quests for a new game
-    fn setup(&mut self) {
+    fn setup(&mut self, tier: i32) {
         self.quests
             .push((Status::Unlocked, 100, Box::new(tutorial::WinBattle)));
         self.quests
@@ -173,8 +310,11 @@ impl QuestList {
             5000,
             beat_enemy::of_class(class::Category::Rare, "beat all rare creatures"),
         ));
-        self.quests
-            .push((Status::Locked(5), 1000, beat_enemy::at_distance(10)));
+        self.quests.push((
+            Status::Locked(5),
+            1000,
+            beat_enemy::at_distance(10 * (tier + 1)),
+        ));
 
         self.quests.push((
             Status::Locked(10),
@@ -201,6 +341,7 @@ impl QuestList {
             30000,
             Box::new(ring::FindAllRings::new()),
         ));
+
         self.quests
             .push((Status::Locked(15), 20000, beat_enemy::shadow()));
         self.quests
@@ -213,6 +354,26 @@ impl QuestList {
         ));
         self.quests
             .push((Status::Locked(50), 1000000, ring::gorthaur()));
+
+        for (_, reward, _) in &mut self.quests {
+            *reward *= tier + 1;
+        }
+    }
+
+    /// Return true if the final boss quest was completed, which enables
+    /// the prestige action.
+    pub fn gorthaur_beaten(&self) -> bool {
+        self.is_completed(&ring::gorthaur().description())
+    }
+
+    /// Return true if a quest with the given description was completed.
+    /// The progress of e.g. `find all rings 15/15` is ignored, so the
+    /// quest can be given as `find all rings`.
+    pub fn is_completed(&self, description: &str) -> bool {
+        let description = without_progress(description);
+        self.quests.iter().any(|(status, _, quest)| {
+            *status == Status::Completed && without_progress(&quest.description()) == description
+        })
     }
 
     /// Pass the event to each of the quests, moving the completed ones to DONE.
@@ -265,6 +426,21 @@ impl QuestList {
     }
 }
 
+/// Remove the trailing progress, e.g. `15/15`, from a quest description.
+fn without_progress(description: &str) -> &str {
+    let is_number = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    match description.rsplit_once(' ') {
+        Some((rest, progress))
+            if progress
+                .split_once('/')
+                .is_some_and(|(done, total)| is_number(done) && is_number(total)) =>
+        {
+            rest
+        }
+        _ => description,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,10 +449,12 @@ mod tests {
     use crate::item;
     use crate::item::Item;
     use crate::location::tests::location_from;
+    use crate::scan::Scan;
+    use std::collections::HashSet;
 
     #[test]
     fn test_quest_status() {
-        let mut quests = QuestList { quests: Vec::new() };
+        let mut quests = QuestList::default();
         quests
             .quests
             .push((Status::Unlocked, 10, Box::new(level::ReachLevel::new(2))));
@@ -301,6 +479,10 @@ mod tests {
         assert_eq!(1, count_status(&quests, Status::Unlocked));
         assert_eq!(1, count_status(&quests, Status::Completed));
         assert_eq!(10, reward);
+        assert!(quests.is_completed("reach level 2"));
+        assert!(!quests.is_completed("reach level 3"));
+        assert!(!quests.is_completed("reach level"));
+        assert!(!quests.is_completed(""));
 
         let reward = quests.handle(&Event::LevelUp {
             count: 2,
@@ -392,6 +574,10 @@ mod tests {
         }
 
         assert_eq!(Status::Completed, game.quests.quests[0].0);
+        assert!(game.quests.is_completed("find all rings"));
+        assert!(game
+            .quests
+            .is_completed(&game.quests.quests[0].2.description()));
     }
 
     #[test]
@@ -409,7 +595,7 @@ mod tests {
         // ruling ring required to spawn the enemy
         game.player.left_ring = Some(item::ring::Ring::Ruling);
 
-        let mut enemy = enemy::spawn(&game.location, &game.player).unwrap();
+        let mut enemy = enemy::spawn(&game.location, &game.player, &Scan::default()).unwrap();
 
         // increase many levels to force the player's victory
         for _ in 0..200 {
@@ -420,6 +606,39 @@ mod tests {
         game.battle(&mut enemy, false, false).unwrap();
 
         assert_eq!(Status::Completed, game.quests.quests[0].0);
+        assert!(game.quests.gorthaur_beaten());
+    }
+
+    #[test]
+    fn clear_repos() {
+        let dir = std::env::temp_dir().join("rpg-clear-repos-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("game/.git")).unwrap();
+        std::fs::create_dir_all(dir.join("site/.git")).unwrap();
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        let dir = dunce::canonicalize(&dir).unwrap();
+        assert!(dungeon::ClearRepos::new(&dir.join("notes"), &HashSet::new()).is_none());
+
+        // offered when first entering a dungeon, counting the cleared ones
+        let mut game = game::Game::new();
+        game.quests = QuestList::default();
+        game.player.level = 5;
+        let site = dir.join("site").to_string_lossy().to_string();
+        game.dungeons.entry(site).or_default().cleared = true;
+        game.visit(Location::from(&dir.join("notes").to_string_lossy()).unwrap())
+            .unwrap();
+        assert!(game.quests.quests.is_empty());
+        game.visit(Location::from(&dir.join("game").to_string_lossy()).unwrap())
+            .unwrap();
+        game.visit(Location::from(&dir.join("site").to_string_lossy()).unwrap())
+            .unwrap();
+        assert_eq!(1, game.quests.quests.len());
+        assert_eq!(Status::Unlocked, game.quests.quests[0].0);
+        assert!(game.quests.quests[0].2.description().ends_with("1/2"));
+
+        dungeon_cleared(&mut game, &dir.join("game").to_string_lossy());
+        assert_eq!(Status::Completed, game.quests.quests[0].0);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     fn count_status(quests: &QuestList, status: Status) -> usize {
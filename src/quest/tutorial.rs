@@ -22,12 +22,12 @@ pub struct BuySword;
 #[typetag::serde]
 impl Quest for BuySword {
     fn description(&self) -> String {
-        "buy a sword".to_string()
+        "buy a weapon".to_string()
     }
 
     fn handle(&mut self, event: &Event) -> bool {
-        if let Event::ItemBought { item: Key::Sword } = event {
-            return true;
+        if let Event::ItemBought { item } = event {
+            return item.is_weapon();
         }
         false
     }
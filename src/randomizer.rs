@@ -1,9 +1,13 @@
 #![allow(dead_code)]
 
 use crate::character::StatusEffect;
+use crate::item::curse::Curse;
+use crate::item::equipment::{Affix, Rarity};
 use crate::location;
+use rand::seq::IteratorRandom;
 use rand::Rng;
 use std::cmp::max;
+use strum::IntoEnumIterator;
 
 /// This trait exposes functions to deal with any element of the game that
 /// needs to incorporate randomness.
@@ -28,7 +32,11 @@ pub trait Randomizer {
 
     fn is_miss(&self, attacker_speed: i32, receiver_speed: i32) -> bool;
 
-    fn is_critical(&self) -> bool;
+    fn is_critical(&self, ratio: u32) -> bool;
+
+    fn is_fatigue_miss(&self, excess_fatigue: i32) -> bool;
+
+    fn camp_ambush(&self, distance: &location::Distance) -> bool;
 
     fn counter_attack(&self) -> bool;
 
@@ -40,10 +48,22 @@ pub trait Randomizer {
 
     fn range(&self, max: i32) -> i32;
 
+    fn equipment_rarity(&self) -> Rarity;
+
+    fn affixes(&self, count: usize) -> Vec<Affix>;
+
     fn gold_chest(&self, distance: &location::Distance) -> bool;
     fn equipment_chest(&self, distance: &location::Distance) -> bool;
     fn ring_chest(&self, distance: &location::Distance) -> bool;
     fn item_chest(&self, distance: &location::Distance) -> bool;
+    fn locked_chest(&self, distance: &location::Distance) -> bool;
+
+    fn lockpick_succeeds(&self) -> bool;
+
+    fn unidentified(&self) -> bool;
+    fn shop_special(&self) -> bool;
+    fn merchant_appears(&self, distance: &location::Distance) -> bool;
+    fn curse(&self) -> Option<Curse>;
 }
 
 #[cfg(not(test))]
@@ -116,9 +136,29 @@ impl Randomizer for DefaultRandomizer {
         false
     }
 
-    fn is_critical(&self) -> bool {
+    /// the ratio is the 1 in N chance of a critical hit
+    fn is_critical(&self, ratio: u32) -> bool {
+        let mut rng = rand::thread_rng();
+        rng.gen_ratio(1, ratio)
+    }
+
+    /// each fatigue point above the threshold adds 1% of miss chance
+    fn is_fatigue_miss(&self, excess_fatigue: i32) -> bool {
+        if excess_fatigue > 0 {
+            let mut rng = rand::thread_rng();
+            return rng.gen_ratio(excess_fatigue as u32, 100);
+        }
+        false
+    }
+
+    fn camp_ambush(&self, distance: &location::Distance) -> bool {
         let mut rng = rand::thread_rng();
-        rng.gen_ratio(1, 20)
+
+        match distance {
+            location::Distance::Near(_) => rng.gen_ratio(1, 10),
+            location::Distance::Mid(_) => rng.gen_ratio(1, 4),
+            location::Distance::Far(_) => rng.gen_ratio(1, 2),
+        }
     }
 
     fn counter_attack(&self) -> bool {
@@ -156,6 +196,21 @@ impl Randomizer for DefaultRandomizer {
         rng.gen_range(0..max)
     }
 
+    fn equipment_rarity(&self) -> Rarity {
+        let mut rng = rand::thread_rng();
+        match rng.gen_range(0..100) {
+            0..=1 => Rarity::Unique,
+            2..=9 => Rarity::Rare,
+            10..=29 => Rarity::Magic,
+            _ => Rarity::Common,
+        }
+    }
+
+    fn affixes(&self, count: usize) -> Vec<Affix> {
+        let mut rng = rand::thread_rng();
+        Affix::iter().choose_multiple(&mut rng, count)
+    }
+
     fn gold_chest(&self, distance: &location::Distance) -> bool {
         let mut rng = rand::thread_rng();
 
@@ -195,6 +250,50 @@ impl Randomizer for DefaultRandomizer {
             location::Distance::Far(_) => rng.gen_ratio(10, 50),
         }
     }
+
+    fn locked_chest(&self, distance: &location::Distance) -> bool {
+        let mut rng = rand::thread_rng();
+
+        match distance {
+            location::Distance::Near(_) => false,
+            location::Distance::Mid(_) => rng.gen_ratio(1, 30),
+            location::Distance::Far(_) => rng.gen_ratio(4, 30),
+        }
+    }
+
+    fn lockpick_succeeds(&self) -> bool {
+        let mut rng = rand::thread_rng();
+        rng.gen_ratio(1, 3)
+    }
+
+    fn unidentified(&self) -> bool {
+        let mut rng = rand::thread_rng();
+        rng.gen_ratio(1, 4)
+    }
+
+    fn shop_special(&self) -> bool {
+        let mut rng = rand::thread_rng();
+        rng.gen_ratio(1, 3)
+    }
+
+    fn merchant_appears(&self, distance: &location::Distance) -> bool {
+        let mut rng = rand::thread_rng();
+
+        match distance {
+            location::Distance::Near(_) => rng.gen_ratio(1, 50),
+            location::Distance::Mid(_) => rng.gen_ratio(1, 30),
+            location::Distance::Far(_) => rng.gen_ratio(1, 30),
+        }
+    }
+
+    fn curse(&self) -> Option<Curse> {
+        let mut rng = rand::thread_rng();
+        if rng.gen_ratio(1, 2) {
+            Some(Curse::random())
+        } else {
+            None
+        }
+    }
 }
 
 /// The test randomizer just exposes the same functions as the default one
@@ -232,7 +331,15 @@ impl Randomizer for TestRandomizer {
         false
     }
 
-    fn is_critical(&self) -> bool {
+    fn is_critical(&self, _ratio: u32) -> bool {
+        false
+    }
+
+    fn is_fatigue_miss(&self, _excess_fatigue: i32) -> bool {
+        false
+    }
+
+    fn camp_ambush(&self, _distance: &location::Distance) -> bool {
         false
     }
 
@@ -256,6 +363,14 @@ impl Randomizer for TestRandomizer {
         max
     }
 
+    fn equipment_rarity(&self) -> Rarity {
+        Rarity::Common
+    }
+
+    fn affixes(&self, count: usize) -> Vec<Affix> {
+        Affix::iter().take(count).collect()
+    }
+
     fn gold_chest(&self, _distance: &location::Distance) -> bool {
         false
     }
@@ -271,6 +386,30 @@ impl Randomizer for TestRandomizer {
     fn ring_chest(&self, _distance: &location::Distance) -> bool {
         false
     }
+
+    fn locked_chest(&self, _distance: &location::Distance) -> bool {
+        false
+    }
+
+    fn lockpick_succeeds(&self) -> bool {
+        true
+    }
+
+    fn unidentified(&self) -> bool {
+        false
+    }
+
+    fn shop_special(&self) -> bool {
+        false
+    }
+
+    fn merchant_appears(&self, _distance: &location::Distance) -> bool {
+        false
+    }
+
+    fn curse(&self) -> Option<Curse> {
+        None
+    }
 }
 
 #[cfg(test)]
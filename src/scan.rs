@@ -0,0 +1,233 @@
+use crate::dungeon::Repo;
+use crate::game::{now, Game};
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Seconds before a cached scan is read again from the filesystem.
+const SCAN_SECONDS: u64 = 60 * 60;
+
+/// Directories whose scans are kept at most, so the saved game doesn't
+/// grow with every directory the hero visits.
+const MAX_SCANS: usize = 100;
+
+/// Entries read at most from a directory, so huge ones don't slow down
+/// the hero's movement.
+const MAX_ENTRIES: usize = 1000;
+
+/// Time spent at most reading a directory, e.g. on slow network drives.
+const SCAN_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Directories modified within these seconds are considered active.
+const ACTIVE_SECONDS: u64 = 24 * 60 * 60;
+
+/// Entries and total file size from which a directory gets an extra chance
+/// of having chests.
+const RICH_ENTRIES: usize = 100;
+const RICH_SIZE: u64 = 100 * 1024 * 1024;
+
+/// What was found in a directory the last time the hero entered it.
+/// Scans are cached per location, so moving through the same
+/// directories again doesn't require reading them.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Scan {
+    /// False if the directory couldn't be read, in which case its
+    /// contents are unknown.
+    pub readable: bool,
+
+    /// Amount of files and subdirectories.
+    pub entries: usize,
+
+    /// Amount of files by extension, e.g. `rs`.
+    pub extensions: HashMap<String, usize>,
+
+    /// Total size in bytes of the files.
+    pub size: u64,
+
+    /// True if the directory had more entries than could be read.
+    pub truncated: bool,
+
+    /// Unix timestamp of the last modification of the directory.
+    pub modified_at: u64,
+
+    /// The git repository that contains the directory, if any.
+    pub repo: Option<Repo>,
+
+    /// Unix timestamp of when the directory was read.
+    scanned_at: u64,
+}
+
+impl Scan {
+    /// Read the entries of the directory, without recursion and up to
+    /// a maximum amount of entries and time.
+    fn read(location: &Location) -> Self {
+        let mut scan = Self {
+            scanned_at: now(),
+            ..Self::default()
+        };
+        let Ok(entries) = fs::read_dir(location.path()) else {
+            return scan;
+        };
+        scan.readable = true;
+        scan.repo = Repo::find(location.path());
+        scan.modified_at = fs::metadata(location.path())
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+
+        let started = Instant::now();
+        for entry in entries.flatten() {
+            if scan.entries >= MAX_ENTRIES || started.elapsed() > SCAN_TIMEOUT {
+                scan.truncated = true;
+                break;
+            }
+
+            scan.entries += 1;
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            scan.size += metadata.len();
+            if let Some(extension) = entry.path().extension() {
+                *scan
+                    .extensions
+                    .entry(extension.to_string_lossy().to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+        scan
+    }
+
+    /// Amount of files with the given extension.
+    pub fn files(&self, extension: &str) -> usize {
+        self.extensions.get(extension).copied().unwrap_or(0)
+    }
+
+    /// Empty directories are safe rooms, where enemies don't appear.
+    pub fn is_safe(&self) -> bool {
+        self.readable && self.entries == 0
+    }
+
+    /// Recently modified directories have more enemy encounters.
+    pub fn is_active(&self) -> bool {
+        self.readable && now() < self.modified_at + ACTIVE_SECONDS
+    }
+
+    /// Extra chances of finding chests, for directories with many entries
+    /// or large files.
+    pub fn richness(&self) -> i32 {
+        let crowded = self.truncated || self.entries >= RICH_ENTRIES;
+        let heavy = self.size >= RICH_SIZE;
+        crowded as i32 + heavy as i32
+    }
+}
+
+/// Return the scan of the hero's location, reading the directory if it
+/// wasn't cached or the cached scan is too old.
+pub fn scan(game: &mut Game) -> Scan {
+    let key = game.location.path_string();
+    if let Some(scan) = game.scans.get(&key) {
+        if now() < scan.scanned_at + SCAN_SECONDS {
+            return scan.clone();
+        }
+    }
+
+    // forget the directory read longest ago to make room for this one
+    if game.scans.len() >= MAX_SCANS && !game.scans.contains_key(&key) {
+        let oldest = game
+            .scans
+            .iter()
+            .min_by_key(|(_, scan)| scan.scanned_at)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            game.scans.remove(&oldest);
+        }
+    }
+
+    let scan = Scan::read(&game.location);
+    game.scans.insert(key, scan.clone());
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_directory() {
+        let dir = std::env::temp_dir().join("rpg-scan-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("lib.rs"), "").unwrap();
+        fs::write(dir.join("README.md"), "").unwrap();
+
+        let mut game = Game::new();
+        game.location = Location::from(&dir.to_string_lossy()).unwrap();
+        let first = scan(&mut game);
+        assert_eq!(4, first.entries);
+        assert_eq!(2, first.files("rs"));
+        assert_eq!(0, first.files("py"));
+        assert_eq!(12, first.size);
+        assert!(first.is_active());
+        assert!(!first.is_safe());
+        assert_eq!(0, first.richness());
+
+        // cached until it gets stale
+        fs::write(dir.join("other.rs"), "").unwrap();
+        assert_eq!(2, scan(&mut game).files("rs"));
+        game.scans
+            .get_mut(&game.location.path_string())
+            .unwrap()
+            .scanned_at = 0;
+        assert_eq!(3, scan(&mut game).files("rs"));
+
+        // empty directories are safe, but not unreadable ones
+        game.location = Location::from(&dir.join("src").to_string_lossy()).unwrap();
+        assert!(scan(&mut game).is_safe());
+        game.location = crate::location::tests::location_from("/rpg/missing/dir");
+        let missing = scan(&mut game);
+        assert!(!missing.readable);
+        assert!(!missing.is_safe());
+        assert!(!missing.is_active());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bounded_cache() {
+        let mut game = Game::new();
+        for i in 0..MAX_SCANS {
+            game.location = crate::location::tests::location_from(&format!("/rpg/missing/{}", i));
+            scan(&mut game);
+        }
+        game.scans.get_mut("/rpg/missing/0").unwrap().scanned_at -= 1;
+
+        game.location = crate::location::tests::location_from("/rpg/missing/new");
+        scan(&mut game);
+        assert_eq!(MAX_SCANS, game.scans.len());
+        assert!(!game.scans.contains_key("/rpg/missing/0"));
+        assert!(game.scans.contains_key("/rpg/missing/new"));
+    }
+
+    #[test]
+    fn bounded_scan() {
+        let dir = std::env::temp_dir().join("rpg-bounded-scan-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..=MAX_ENTRIES {
+            fs::write(dir.join(format!("{}.txt", i)), "").unwrap();
+        }
+
+        let scan = Scan::read(&Location::from(&dir.to_string_lossy()).unwrap());
+        assert_eq!(MAX_ENTRIES, scan.entries);
+        assert!(scan.truncated);
+        assert_eq!(1, scan.richness());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+use crate::item::key::Key;
+use crate::item::ring::Ring;
+use crate::quest::Event;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Lifetime counters of the hero deeds. Unlike the rest of the game state
+/// these are preserved across deaths. They are updated from the same events
+/// that feed the quest list.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Stats {
+    pub battles_won: HashMap<String, i32>,
+    pub battles_lost: HashMap<String, i32>,
+    pub gold_earned: i32,
+    pub gold_spent: i32,
+    pub deepest_distance: i32,
+    pub items_used: HashMap<Key, i32>,
+    pub items_sold: HashMap<Key, i32>,
+    pub chests_found: i32,
+    pub rings_found: HashSet<Ring>,
+    pub bribes: i32,
+    pub runs: i32,
+    pub deaths: HashMap<String, i32>,
+}
+
+impl Stats {
+    /// Update the counters based on the given game event.
+    pub fn handle(&mut self, event: &Event) {
+        match event {
+            Event::BattleWon { enemy, .. } => {
+                *self.battles_won.entry(enemy.name()).or_default() += 1;
+            }
+            Event::BattleLost { enemy } => {
+                *self.battles_lost.entry(enemy.name()).or_default() += 1;
+            }
+            Event::Died { cause } => {
+                *self.deaths.entry(cause.to_string()).or_default() += 1;
+            }
+            Event::GoldEarned { amount } => self.gold_earned += amount,
+            Event::GoldSpent { amount } => self.gold_spent += amount,
+            Event::Bribed { amount } => {
+                self.bribes += 1;
+                self.gold_spent += amount;
+            }
+            Event::RanAway => self.runs += 1,
+            Event::LocationVisited { location } => {
+                let distance = location.distance_from_home().len();
+                self.deepest_distance = std::cmp::max(self.deepest_distance, distance);
+            }
+            Event::ItemUsed { item } => {
+                *self.items_used.entry(item.clone()).or_default() += 1;
+            }
+            Event::ItemSold { item } => {
+                *self.items_sold.entry(item.clone()).or_default() += 1;
+            }
+            Event::ItemAdded {
+                item: Key::Ring(ring),
+            } => {
+                self.rings_found.insert(ring.clone());
+            }
+            Event::ChestFound => self.chests_found += 1,
+            _ => {}
+        }
+    }
+
+    /// Flatten the counters into sorted (stat, detail, value) rows,
+    /// e.g. ("battles_won", "rat", 3).
+    pub fn rows(&self) -> Vec<(String, String, i32)> {
+        let mut rows = Vec::new();
+
+        let mut push_map = |name: &str, map: &HashMap<String, i32>| {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort();
+            for (detail, value) in entries {
+                rows.push((name.to_string(), detail.to_string(), *value));
+            }
+        };
+        push_map("battles_won", &self.battles_won);
+        push_map("battles_lost", &self.battles_lost);
+        push_map("deaths", &self.deaths);
+
+        let items_used = self
+            .items_used
+            .iter()
+            .map(|(k, v)| (k.to_string(), *v))
+            .collect();
+        push_map("items_used", &items_used);
+
+        let items_sold = self
+            .items_sold
+            .iter()
+            .map(|(k, v)| (k.to_string(), *v))
+            .collect();
+        push_map("items_sold", &items_sold);
+
+        for (name, value) in [
+            ("gold_earned", self.gold_earned),
+            ("gold_spent", self.gold_spent),
+            ("deepest_distance", self.deepest_distance),
+            ("chests_found", self.chests_found),
+            ("rings_found", self.rings_found.len() as i32),
+            ("bribes", self.bribes),
+            ("runs", self.runs),
+        ] {
+            rows.push((name.to_string(), String::new(), value));
+        }
+        rows
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("stat,detail,value\n");
+        for (stat, detail, value) in self.rows() {
+            csv.push_str(&format!("{},{},{}\n", stat, detail, value));
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::Character;
+    use crate::location::tests::location_from;
+
+    #[test]
+    fn handle_events() {
+        let mut stats = Stats::default();
+        let enemy = Character::player();
+        let location = location_from("~/1/2/3");
+
+        stats.handle(&Event::BattleWon {
+            enemy: &enemy,
+            location: location.clone(),
+        });
+        stats.handle(&Event::BattleWon {
+            enemy: &enemy,
+            location: location.clone(),
+        });
+        stats.handle(&Event::BattleLost { enemy: &enemy });
+        stats.handle(&Event::Died {
+            cause: String::from("poison"),
+        });
+        stats.handle(&Event::GoldEarned { amount: 100 });
+        stats.handle(&Event::Bribed { amount: 30 });
+        stats.handle(&Event::GoldSpent { amount: 20 });
+        stats.handle(&Event::ItemAdded {
+            item: Key::Ring(Ring::Void),
+        });
+        stats.handle(&Event::ItemAdded {
+            item: Key::Ring(Ring::Void),
+        });
+        stats.handle(&Event::ItemUsed { item: Key::Potion });
+
+        assert_eq!(Some(&2), stats.battles_won.get("warrior"));
+        assert_eq!(Some(&1), stats.battles_lost.get("warrior"));
+        assert_eq!(Some(&1), stats.deaths.get("poison"));
+        assert_eq!(100, stats.gold_earned);
+        assert_eq!(50, stats.gold_spent);
+        assert_eq!(1, stats.bribes);
+        assert_eq!(1, stats.rings_found.len());
+        assert_eq!(Some(&1), stats.items_used.get(&Key::Potion));
+
+        let csv = stats.to_csv();
+        assert!(csv.starts_with("stat,detail,value\n"));
+        assert!(csv.contains("battles_won,warrior,2\n"));
+        assert!(csv.contains("gold_spent,,50\n"));
+    }
+}